@@ -4,12 +4,12 @@ use super::{
     field::Field,
     layout::{Board, BoardCoordinates},
 };
-use crate::engine::Player;
+use crate::engine::Color;
 
 #[derive(Debug, Clone, Copy)]
 pub struct ChessPiece {
     pub piece_type: ChessPieceType,
-    pub player: Player,
+    pub player: Color,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -38,10 +38,10 @@ pub fn get_movements(field: &Field, board: &Board) -> Vec<BoardCoordinates> {
 }
 
 // THIS LOOKS LIKE TERRIBLE DESIGN HELP
-pub fn front(coordinates: BoardCoordinates, player: Player) -> Option<BoardCoordinates> {
+pub fn front(coordinates: BoardCoordinates, player: Color) -> Option<BoardCoordinates> {
     match player {
-        Player::User => coordinates.up(),
-        Player::Opponent => coordinates.down(),
+        Color::White => coordinates.up(),
+        Color::Black => coordinates.down(),
     }
 }
 
@@ -372,8 +372,8 @@ impl ChessPiece {
     pub(crate) fn can_change(&self, coordinates: BoardCoordinates) -> bool {
         if self.piece_type == ChessPieceType::Pawn {
             match self.player {
-                Player::User => coordinates.up().is_none(),
-                Player::Opponent => coordinates.down().is_none(),
+                Color::White => coordinates.up().is_none(),
+                Color::Black => coordinates.down().is_none(),
             }
         } else {
             false
@@ -0,0 +1,124 @@
+use super::{
+    chesspiece::{ChessPiece, ChessPieceType},
+    layout::{Board, BoardCoordinates},
+};
+use crate::engine::Color;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1";
+
+/// Reads the pieces and the side to move from a FEN. Castling rights, en
+/// passant and the halfmove clock are accepted but ignored, since the game
+/// has no use for them yet; `fullmove_number` reads the last field.
+pub fn from_fen(fen: &str) -> Result<(Board, Color), String> {
+    let mut fields = fen.split_whitespace();
+    let placement = fields.next().ok_or("The FEN is empty")?;
+    let player = match fields.next() {
+        Some("w") | None => Color::White,
+        Some("b") => Color::Black,
+        Some(other) => return Err(format!("Invalid side to move in FEN: {}", other)),
+    };
+
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(format!("A FEN needs 8 ranks: {}", placement));
+    }
+    let mut board = Board::default();
+    // The first rank of a FEN is Black's back rank, the top row of the board.
+    for (x, rank) in ranks.iter().enumerate() {
+        let mut y = 0;
+        for symbol in rank.chars() {
+            if let Some(empty) = symbol.to_digit(10) {
+                y += empty;
+                continue;
+            }
+            let coordinates = BoardCoordinates::from_coordinates(x as u32, y)
+                .ok_or_else(|| format!("Rank {} of the FEN is too long", 8 - x))?;
+            let piece_type = symbol
+                .to_ascii_uppercase()
+                .to_string()
+                .parse::<ChessPieceType>()
+                .map_err(|_| format!("Invalid piece in FEN: {}", symbol))?;
+            let player = if symbol.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            board = board.add_replace_piece(coordinates, ChessPiece { piece_type, player });
+            y += 1;
+        }
+        if y != 8 {
+            return Err(format!("Rank {} of the FEN does not have 8 fields", 8 - x));
+        }
+    }
+    for color in [Color::White, Color::Black] {
+        let kings = board
+            .get_all_fields_by_player(&color)
+            .into_iter()
+            .filter(|field| field.piece.unwrap().piece_type == ChessPieceType::King)
+            .count();
+        if kings != 1 {
+            return Err(format!("{} must have exactly one king", color));
+        }
+    }
+    Ok((board, player))
+}
+
+/// The number of the next move in a FEN, or 1 if it has none.
+pub fn fullmove_number(fen: &str) -> usize {
+    fen.split_whitespace()
+        .nth(5)
+        .and_then(|number| number.parse().ok())
+        .filter(|number| *number > 0)
+        .unwrap_or(1)
+}
+
+pub fn to_fen(board: &Board, player: Color, fullmove: usize) -> String {
+    let mut placement = String::new();
+    for x in 0..8 {
+        let mut empty = 0;
+        for y in 0..8 {
+            let field = board.at(&BoardCoordinates::from_coordinates(x, y).unwrap());
+            match field.piece {
+                Some(piece) => {
+                    if empty > 0 {
+                        placement.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    let symbol = piece.piece_type.to_string();
+                    placement.push_str(&match piece.player {
+                        Color::White => symbol,
+                        Color::Black => symbol.to_ascii_lowercase(),
+                    });
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+        if x < 7 {
+            placement.push('/');
+        }
+    }
+    let side = match player {
+        Color::White => "w",
+        Color::Black => "b",
+    };
+    format!("{} {} - - 0 {}", placement, side, fullmove)
+}
+
+#[test]
+fn fen_round_trip() {
+    let (board, player) = from_fen(STARTING_FEN).unwrap();
+    assert_eq!(player, Color::White);
+    let standard = Board::default().fill_standard_pieces();
+    assert_eq!(to_fen(&standard, Color::White, 1), STARTING_FEN);
+    assert_eq!(to_fen(&board, player, 1), STARTING_FEN);
+
+    let fen = "4k3/8/8/3q4/8/8/4N3/4K3 b - - 0 40";
+    let (board, player) = from_fen(fen).unwrap();
+    assert_eq!(to_fen(&board, player, 40), fen);
+    assert!(from_fen("8/8/8/8/8/8/8/8 w - - 0 1").is_err());
+    assert!(from_fen("4k3/8/8/9/8/8/8/4K3 w - - 0 1").is_err());
+    assert!(from_fen("4k3/8/8/8/8/8/4K3 w - - 0 1").is_err());
+}
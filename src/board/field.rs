@@ -1,4 +1,4 @@
-use crate::engine::Player;
+use crate::engine::Color;
 
 use super::{chesspiece::ChessPiece, layout::BoardCoordinates};
 
@@ -24,7 +24,7 @@ impl Field {
         self.piece = None;
     }
 
-    pub fn check_player(&self) -> Option<Player> {
+    pub fn check_player(&self) -> Option<Color> {
         self.piece.map(|piece| piece.player)
     }
 }
@@ -1,6 +1,6 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
-use crate::{engine::Player, interface};
+use crate::engine::Color;
 
 use super::{
     chesspiece::{ChessPiece, ChessPieceType},
@@ -18,14 +18,14 @@ impl Board {
                     BoardCoordinates::from_coordinates(6, y).unwrap(),
                     ChessPiece {
                         piece_type: super::chesspiece::ChessPieceType::Pawn,
-                        player: Player::User,
+                        player: Color::White,
                     },
                 )
                 .add_replace_piece(
                     BoardCoordinates::from_coordinates(1, y).unwrap(),
                     ChessPiece {
                         piece_type: super::chesspiece::ChessPieceType::Pawn,
-                        player: Player::Opponent,
+                        player: Color::Black,
                     },
                 )
         }
@@ -34,112 +34,112 @@ impl Board {
                 BoardCoordinates::from_index(56).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Rook,
-                    player: Player::User,
+                    player: Color::White,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(57).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Knight,
-                    player: Player::User,
+                    player: Color::White,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(58).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Bishop,
-                    player: Player::User,
+                    player: Color::White,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(59).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Queen,
-                    player: Player::User,
+                    player: Color::White,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(60).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::King,
-                    player: Player::User,
+                    player: Color::White,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(61).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Bishop,
-                    player: Player::User,
+                    player: Color::White,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(62).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Knight,
-                    player: Player::User,
+                    player: Color::White,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(63).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Rook,
-                    player: Player::User,
+                    player: Color::White,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(0).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Rook,
-                    player: Player::Opponent,
+                    player: Color::Black,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(1).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Knight,
-                    player: Player::Opponent,
+                    player: Color::Black,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(2).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Bishop,
-                    player: Player::Opponent,
+                    player: Color::Black,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(3).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Queen,
-                    player: Player::Opponent,
+                    player: Color::Black,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(4).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::King,
-                    player: Player::Opponent,
+                    player: Color::Black,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(5).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Bishop,
-                    player: Player::Opponent,
+                    player: Color::Black,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(6).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Knight,
-                    player: Player::Opponent,
+                    player: Color::Black,
                 },
             )
             .add_replace_piece(
                 BoardCoordinates::from_index(7).unwrap(),
                 ChessPiece {
                     piece_type: super::chesspiece::ChessPieceType::Rook,
-                    player: Player::Opponent,
+                    player: Color::Black,
                 },
             );
         self
@@ -153,13 +153,28 @@ impl Board {
         self.0[(coordinates.x * 8 + coordinates.y) as usize]
     }
 
-    pub fn get_all_fields_by_player(&self, player: &Player) -> Vec<Field> {
+    pub fn get_all_fields_by_player(&self, player: &Color) -> Vec<Field> {
         self.0
             .into_iter()
             .filter(|field| field.check_player().contains(player))
             .collect()
     }
 
+    /// A lone king, or a king with a single bishop or knight, can never checkmate.
+    pub fn has_mating_material(&self, player: &Color) -> bool {
+        let pieces: Vec<ChessPieceType> = self
+            .get_all_fields_by_player(player)
+            .into_iter()
+            .filter_map(|field| field.piece)
+            .map(|piece| piece.piece_type)
+            .filter(|piece_type| *piece_type != ChessPieceType::King)
+            .collect();
+        !matches!(
+            pieces[..],
+            [] | [ChessPieceType::Bishop] | [ChessPieceType::Knight]
+        )
+    }
+
     pub(crate) fn remove_piece(mut self, coordinates: BoardCoordinates) -> Board {
         self.at_mut(&coordinates).remove_piece();
         self
@@ -168,13 +183,8 @@ impl Board {
     pub(crate) fn add_replace_piece(
         mut self,
         coordinates: BoardCoordinates,
-        mut piece: super::chesspiece::ChessPiece,
+        piece: super::chesspiece::ChessPiece,
     ) -> Board {
-        if piece.can_change(coordinates) {
-            let piece_type: ChessPieceType =
-                interface::get_input("Choose a piece to replace the pawn: ");
-            piece.piece_type = piece_type
-        }
         self.at_mut(&coordinates).add_replace_piece(piece);
         self
     }
@@ -206,6 +216,33 @@ impl BoardCoordinates {
         }
     }
 
+    pub fn index(self) -> u32 {
+        self.x * 8 + self.y
+    }
+
+    /// The file letter, `a` on White's left.
+    pub fn file(self) -> char {
+        (b'a' + self.y as u8) as char
+    }
+
+    /// The rank number, counted from White's side of the board.
+    pub fn rank(self) -> u32 {
+        8 - self.x
+    }
+
+    /// Maps a position on the screen to the field drawn there. With Black at
+    /// the bottom the board is turned around, so every field swaps with the
+    /// opposite one.
+    pub fn oriented(self, bottom: Color) -> Self {
+        match bottom {
+            Color::White => self,
+            Color::Black => Self {
+                x: 7 - self.x,
+                y: 7 - self.y,
+            },
+        }
+    }
+
     pub fn up(self) -> Option<Self> {
         if self.x > 0 {
             Some(Self {
@@ -251,37 +288,29 @@ impl BoardCoordinates {
     }
 }
 
+/// The standard name of the field, e.g. `e4`.
+impl fmt::Display for BoardCoordinates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
+
+/// Parses a standard field name like `e4`, in either case.
 impl FromStr for BoardCoordinates {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() < 2 {
-            return Err("Inputted String for Board Coordinates is too short!".to_owned());
-        }
-        let letter = s.get(..1).unwrap();
-        let y = match letter {
-            "A" => 0,
-            "B" => 1,
-            "C" => 2,
-            "D" => 3,
-            "E" => 4,
-            "F" => 5,
-            "G" => 6,
-            "H" => 7,
-            _ => return Err(format!("Invalid letter for Board Coordinates: {}", letter)),
+        let mut chars = s.trim().chars();
+        let (Some(file), Some(rank), None) = (chars.next(), chars.next(), chars.next()) else {
+            return Err(format!("Invalid field: {}", s.trim()));
         };
-        let digit = s.get(1..2).unwrap();
-        let x = digit
-            .parse::<u32>()
-            .map_err(|_| format!("Invalid digit for Board Coordinates: {}", digit))
-            .and_then(|digit| {
-                digit
-                    .checked_sub(1)
-                    .ok_or_else(|| format!("Invalid digit for Board Coordinates: {}", digit))
-            })?;
-        let coordinates =
-            Self::from_coordinates(x, y).ok_or_else(|| "Invalid Board Coordinates!".to_owned());
-        print!("[debug] Coordinates: {:?}", coordinates);
-        coordinates
+        let y = match file.to_ascii_lowercase() {
+            file @ 'a'..='h' => file as u32 - 'a' as u32,
+            _ => return Err(format!("Invalid letter for Board Coordinates: {}", file)),
+        };
+        match rank.to_digit(10) {
+            Some(rank @ 1..=8) => Ok(Self { x: 8 - rank, y }),
+            _ => Err(format!("Invalid digit for Board Coordinates: {}", rank)),
+        }
     }
 }
 
@@ -294,6 +323,18 @@ fn coordinates_check() {
     assert_eq!(BoardCoordinates { x: 1, y: 4 }, coordinates);
 }
 
+#[test]
+fn coordinates_orientation() {
+    let coordinates = BoardCoordinates::from_str("b7").unwrap();
+    assert_eq!(coordinates.to_string(), "b7");
+    assert_eq!(coordinates.oriented(Color::White), coordinates);
+    assert_eq!(coordinates.oriented(Color::Black).to_string(), "g2");
+    assert_eq!(
+        coordinates.oriented(Color::Black).oriented(Color::Black),
+        coordinates
+    );
+}
+
 impl Default for Board {
     fn default() -> Self {
         Board(array_init::array_init::<_, Field, 64>(|i| {
@@ -1,4 +1,5 @@
 pub mod chesspiece;
+pub mod fen;
 pub mod field;
 /// This will be mostly a linker between different structures (board layout, pieces etc.)
 pub mod layout;
@@ -0,0 +1,342 @@
+use std::{fmt, str::FromStr};
+
+/// A JSON value, as much as the game server needs to read requests and
+/// write replies.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys stay in the order they were written in.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Adds a field to an object. Anything else is returned as it is.
+    pub fn with(mut self, key: &str, value: Json) -> Self {
+        if let Json::Object(fields) = &mut self {
+            fields.push((key.to_string(), value));
+        }
+        self
+    }
+
+    /// Builds an object from `(key, value)` pairs.
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Self {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+}
+
+impl From<&str> for Json {
+    fn from(text: &str) -> Self {
+        Json::String(text.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(text: String) -> Self {
+        Json::String(text)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(number: i64) -> Self {
+        Json::Number(number as f64)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(items: Vec<T>) -> Self {
+        Json::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Compact JSON, without any whitespace.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(number) if !number.is_finite() => write!(f, "null"),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(text) => write_string(f, text),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl FromStr for Json {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            position: 0,
+        };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            return Err(format!("Unexpected text after JSON at {}", parser.position));
+        }
+        Ok(value)
+    }
+}
+
+/// How deep arrays and objects may nest, so that a hostile body can't
+/// overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or("Unexpected end of JSON")?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(format!(
+                "Expected '{}' but found '{}' at {}",
+                expected,
+                c,
+                self.position - 1
+            )),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    /// Parses a value inside `depth` arrays and objects.
+    fn value(&mut self, depth: usize) -> Result<Json, String> {
+        self.skip_whitespace();
+        if depth > MAX_DEPTH {
+            return Err("JSON nested too deeply".to_string());
+        }
+        match self.peek().ok_or("Unexpected end of JSON")? {
+            'n' => self.keyword("null", Json::Null),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => {
+                self.position += 1;
+                let mut items = vec![];
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.position += 1;
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.next()? {
+                        ',' => continue,
+                        ']' => return Ok(Json::Array(items)),
+                        c => return Err(format!("Expected ',' or ']' but found '{}'", c)),
+                    }
+                }
+            }
+            '{' => {
+                self.position += 1;
+                let mut fields = vec![];
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.position += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(':')?;
+                    fields.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.next()? {
+                        ',' => continue,
+                        '}' => return Ok(Json::Object(fields)),
+                        c => return Err(format!("Expected ',' or '}}' but found '{}'", c)),
+                    }
+                }
+            }
+            _ => self.number(),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(text),
+                '\\' => text.push(match self.next()? {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => {
+                        let digits: String =
+                            (0..4).map(|_| self.next()).collect::<Result<_, _>>()?;
+                        u32::from_str_radix(&digits, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("Invalid escape: \\u{}", digits))?
+                    }
+                    c => c,
+                }),
+                c => text.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+        {
+            self.position += 1;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| format!("Invalid JSON value at {}", start))
+    }
+}
+
+#[test]
+fn json_round_trip() {
+    let text =
+        r#"{"id":3,"moves":["e2e4","e7e5"],"clock":null,"over":false,"note":"a \"quote\"\n"}"#;
+    let json: Json = text.parse().unwrap();
+    assert_eq!(json.to_string(), text);
+    assert_eq!(json.get("id").and_then(Json::as_f64), Some(3.0));
+    assert_eq!(
+        json.get("moves").and_then(Json::as_array).map(<[_]>::len),
+        Some(2)
+    );
+    assert_eq!(
+        " { \"a\" : [ 1.5 , -2e3 ] } ".parse::<Json>().unwrap(),
+        Json::object([("a", vec![Json::Number(1.5), Json::Number(-2000.0)].into())])
+    );
+    assert!("{\"a\":}".parse::<Json>().is_err());
+    assert!("[1] 2".parse::<Json>().is_err());
+}
+
+#[test]
+fn rejects_deep_nesting() {
+    let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+    assert!(nested.parse::<Json>().is_ok());
+    assert_eq!(
+        "[".repeat(200_000).parse::<Json>().unwrap_err(),
+        "JSON nested too deeply"
+    );
+    assert_eq!(
+        "{\"a\":".repeat(200_000).parse::<Json>().unwrap_err(),
+        "JSON nested too deeply"
+    );
+}
@@ -1,3 +1,6 @@
+pub mod json;
+pub mod websocket;
+
 /// This is made for common functions which can be used everywhere
 ///
 
@@ -0,0 +1,286 @@
+use std::io::{self, Read, Write};
+
+/// Messages longer than this are refused.
+const MAX_FRAME: u64 = 1 << 20;
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+pub const CONTINUATION: u8 = 0x0;
+pub const TEXT: u8 = 0x1;
+pub const CLOSE: u8 = 0x8;
+pub const PING: u8 = 0x9;
+pub const PONG: u8 = 0xa;
+
+/// The close code for a peer that broke the protocol.
+pub const PROTOCOL_ERROR: u16 = 1002;
+
+/// A WebSocket message, with the payload unmasked. Binary messages are not
+/// supported.
+#[derive(Debug, PartialEq)]
+pub enum Frame {
+    Text(String),
+    Ping(Vec<u8>),
+    Pong,
+    /// With the status code, if the peer gave one.
+    Close(Option<u16>),
+}
+
+/// The `Sec-WebSocket-Accept` answer to a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key.trim(), GUID).as_bytes()))
+}
+
+/// Reads the messages of one side of a WebSocket, putting fragmented text
+/// back together. Frames from a client must be masked, those from a server
+/// must not; anything else is an `InvalidData` error.
+pub struct FrameReader<R> {
+    reader: R,
+    from_client: bool,
+    /// The fragments of a text message so far.
+    text: Option<Vec<u8>>,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R, from_client: bool) -> Self {
+        FrameReader {
+            reader,
+            from_client,
+            text: None,
+        }
+    }
+
+    /// The next message. Control frames may come between the fragments of
+    /// a text message and are returned as they arrive.
+    pub fn read_frame(&mut self) -> io::Result<Frame> {
+        loop {
+            let (fin, opcode, payload) = self.read_single()?;
+            let text = match opcode {
+                CLOSE | PING | PONG if !fin => {
+                    return Err(invalid("A control frame is fragmented"))
+                }
+                CLOSE => {
+                    let status = payload
+                        .get(..2)
+                        .map(|code| u16::from_be_bytes([code[0], code[1]]));
+                    return Ok(Frame::Close(status));
+                }
+                PING => return Ok(Frame::Ping(payload)),
+                PONG => return Ok(Frame::Pong),
+                TEXT if self.text.is_some() => {
+                    return Err(invalid("A message started before the last one ended"))
+                }
+                TEXT => payload,
+                CONTINUATION => {
+                    let mut text = self
+                        .text
+                        .take()
+                        .ok_or_else(|| invalid("A continuation of no message"))?;
+                    text.extend(payload);
+                    text
+                }
+                _ => return Err(invalid("Unsupported frame")),
+            };
+            if text.len() as u64 > MAX_FRAME {
+                return Err(invalid("The message is too large"));
+            }
+            if !fin {
+                self.text = Some(text);
+                continue;
+            }
+            return String::from_utf8(text)
+                .map(Frame::Text)
+                .map_err(|_| invalid("The text is not UTF-8"));
+        }
+    }
+
+    /// Whether the frame is the last of its message, its opcode and its
+    /// unmasked payload.
+    fn read_single(&mut self) -> io::Result<(bool, u8, Vec<u8>)> {
+        let reader = &mut self.reader;
+        let mut head = [0; 2];
+        reader.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0f;
+        let mut length = u64::from(head[1] & 0x7f);
+        if length == 126 {
+            let mut bytes = [0; 2];
+            reader.read_exact(&mut bytes)?;
+            length = u64::from(u16::from_be_bytes(bytes));
+        } else if length == 127 {
+            let mut bytes = [0; 8];
+            reader.read_exact(&mut bytes)?;
+            length = u64::from_be_bytes(bytes);
+        }
+        if length > MAX_FRAME {
+            return Err(invalid("The frame is too large"));
+        }
+        let masked = head[1] & 0x80 != 0;
+        if masked != self.from_client {
+            return Err(invalid(if self.from_client {
+                "A frame from the client is not masked"
+            } else {
+                "A frame from the server is masked"
+            }));
+        }
+        let mut mask = [0; 4];
+        if masked {
+            reader.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0; length as usize];
+        reader.read_exact(&mut payload)?;
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+        Ok((fin, opcode, payload))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Writes a single final frame. Servers send frames unmasked, clients
+/// must pass a `mask`.
+pub fn write_frame(
+    writer: &mut impl Write,
+    opcode: u8,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    let masked = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        length @ 0..=125 => frame.push(masked | length as u8),
+        length @ 126..=0xffff => {
+            frame.push(masked | 126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(masked | 127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            frame.extend(
+                payload
+                    .iter()
+                    .enumerate()
+                    .map(|(index, byte)| byte ^ mask[index % 4]),
+            );
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (index, word) in block.chunks(4).enumerate() {
+            words[index] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for index in 16..80 {
+            words[index] =
+                (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16])
+                    .rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (index, word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+    let mut digest = [0; 20];
+    for (chunk, value) in digest.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+#[test]
+fn handshake_and_frames() {
+    // The example from RFC 6455.
+    assert_eq!(
+        accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+    assert_eq!(base64(b"ab"), "YWI=");
+
+    let mut bytes = vec![];
+    let long = "x".repeat(300);
+    write_frame(&mut bytes, TEXT, long.as_bytes(), None).unwrap();
+    write_frame(&mut bytes, CLOSE, &[], None).unwrap();
+    let mut reader = FrameReader::new(&bytes[..], false);
+    assert_eq!(reader.read_frame().unwrap(), Frame::Text(long));
+    assert_eq!(reader.read_frame().unwrap(), Frame::Close(None));
+
+    // A client's message in two fragments with a ping in between.
+    let mut bytes = vec![];
+    write_frame(&mut bytes, TEXT, b"e2", Some([1, 2, 3, 4])).unwrap();
+    bytes[0] &= 0x7f;
+    write_frame(&mut bytes, PING, b"?", Some([5; 4])).unwrap();
+    write_frame(&mut bytes, CONTINUATION, b"e4", Some([1, 2, 3, 4])).unwrap();
+    let mut reader = FrameReader::new(&bytes[..], true);
+    assert_eq!(reader.read_frame().unwrap(), Frame::Ping(b"?".to_vec()));
+    assert_eq!(
+        reader.read_frame().unwrap(),
+        Frame::Text("e2e4".to_string())
+    );
+
+    let mut bytes = vec![];
+    write_frame(&mut bytes, TEXT, b"e2e4", None).unwrap();
+    let error = FrameReader::new(&bytes[..], true).read_frame().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    let mut bytes = vec![];
+    write_frame(&mut bytes, CONTINUATION, b"e4", None).unwrap();
+    assert!(FrameReader::new(&bytes[..], false).read_frame().is_err());
+}
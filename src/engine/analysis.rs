@@ -0,0 +1,258 @@
+use super::{
+    game_state::{check_if_king_in_check, GameState},
+    moves::{apply_move, legal_moves, san, Move},
+    pgn::{annotated_pgn, Annotation},
+    search::{Engine, SearchOptions},
+    Color,
+};
+use crate::board::layout::Board;
+
+/// Evaluations are capped, so that missing a mate does not count for
+/// thousands of centipawns.
+const CAP: i32 = 1000;
+
+/// How bad a move was, by the centipawns it lost against the engine's choice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    fn of(loss: i32) -> Option<Self> {
+        match loss {
+            300.. => Some(Judgement::Blunder),
+            100.. => Some(Judgement::Mistake),
+            50.. => Some(Judgement::Inaccuracy),
+            _ => None,
+        }
+    }
+
+    /// The annotation that goes after the move, e.g. `??` for a blunder.
+    pub fn nag(self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "?!",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+}
+
+/// What the engine thinks of a single move.
+#[derive(Debug, Clone)]
+pub struct Review {
+    pub mv: Move,
+    pub player: Color,
+    /// The evaluation after the engine's choice and after the move played,
+    /// both in centipawns for `player`.
+    pub best_score: i32,
+    pub score: i32,
+    pub loss: i32,
+    pub judgement: Option<Judgement>,
+    /// The engine's choice in standard notation, if it was another move.
+    pub better: Option<String>,
+}
+
+pub struct Analysis {
+    pub reviews: Vec<Review>,
+}
+
+/// The evaluation for the side to move and the engine's choice.
+fn evaluate(engine: &Engine, board: &Board, player: Color) -> (i32, Option<Move>) {
+    if legal_moves(board, &player).is_empty() {
+        let score = if check_if_king_in_check(board, &player) {
+            -CAP
+        } else {
+            0
+        };
+        return (score, None);
+    }
+    let result = engine.search(board, player);
+    (result.score.clamp(-CAP, CAP), result.best_move)
+}
+
+/// Runs the engine over every position of the game. `progress` hears how
+/// many positions are done out of how many.
+pub fn analyze(
+    game_state: &GameState,
+    options: SearchOptions,
+    mut progress: impl FnMut(usize, usize),
+) -> Analysis {
+    let engine = Engine::new(options);
+    let mut positions = vec![(game_state.start_board.clone(), game_state.start_player)];
+    for mv in &game_state.history {
+        let (board, player) = positions.last().unwrap();
+        positions.push((apply_move(board.clone(), mv), player.switch()));
+    }
+    let evaluations: Vec<(i32, Option<Move>)> = positions
+        .iter()
+        .enumerate()
+        .map(|(done, (board, player))| {
+            progress(done, positions.len());
+            evaluate(&engine, board, *player)
+        })
+        .collect();
+
+    let reviews = game_state
+        .history
+        .iter()
+        .enumerate()
+        .map(|(ply, mv)| {
+            let (board, player) = &positions[ply];
+            let (best_score, best) = evaluations[ply];
+            let score = -evaluations[ply + 1].0;
+            let better = best.filter(|best| best != mv);
+            let loss = if better.is_some() {
+                (best_score - score).max(0)
+            } else {
+                0
+            };
+            Review {
+                mv: *mv,
+                player: *player,
+                best_score,
+                score,
+                loss,
+                judgement: Judgement::of(loss),
+                better: better.map(|best| san(board, &best)),
+            }
+        })
+        .collect();
+    Analysis { reviews }
+}
+
+/// The chance to win in percent for an evaluation in centipawns.
+fn win_chance(score: i32) -> f64 {
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * score as f64).exp()) - 1.0)
+}
+
+impl Analysis {
+    fn by(&self, player: Color) -> impl Iterator<Item = &Review> {
+        self.reviews
+            .iter()
+            .filter(move |review| review.player == player)
+    }
+
+    /// 100 for playing the engine's moves throughout, falling the more the
+    /// chances to win dropped with each move.
+    pub fn accuracy(&self, player: Color) -> f64 {
+        let accuracies: Vec<f64> = self
+            .by(player)
+            .map(|review| {
+                let dropped = (win_chance(review.best_score) - win_chance(review.score)).max(0.0);
+                (103.1668 * (-0.04354 * dropped).exp() - 3.1669).clamp(0.0, 100.0)
+            })
+            .collect();
+        if accuracies.is_empty() {
+            100.0
+        } else {
+            accuracies.iter().sum::<f64>() / accuracies.len() as f64
+        }
+    }
+
+    pub fn average_loss(&self, player: Color) -> f64 {
+        let losses: Vec<i32> = self.by(player).map(|review| review.loss).collect();
+        losses.iter().sum::<i32>() as f64 / losses.len().max(1) as f64
+    }
+
+    pub fn count(&self, player: Color, judgement: Judgement) -> usize {
+        self.by(player)
+            .filter(|review| review.judgement == Some(judgement))
+            .count()
+    }
+
+    /// One line per side, e.g. `White: accuracy 87.5%, average loss 31
+    /// centipawns, 2 inaccuracies, 1 mistake, 0 blunders`.
+    pub fn summary(&self, label: impl Fn(Color) -> String) -> String {
+        [Color::White, Color::Black]
+            .into_iter()
+            .map(|player| {
+                let count = |judgement, one, many| {
+                    let count = self.count(player, judgement);
+                    format!("{} {}", count, if count == 1 { one } else { many })
+                };
+                format!(
+                    "{}: accuracy {:.1}%, average loss {:.0} centipawns, {}, {}, {}\n",
+                    label(player),
+                    self.accuracy(player),
+                    self.average_loss(player),
+                    count(Judgement::Inaccuracy, "inaccuracy", "inaccuracies"),
+                    count(Judgement::Mistake, "mistake", "mistakes"),
+                    count(Judgement::Blunder, "blunder", "blunders"),
+                )
+            })
+            .collect()
+    }
+
+    pub fn moves(&self) -> Vec<Move> {
+        self.reviews.iter().map(|review| review.mv).collect()
+    }
+
+    /// The evaluation after every move, for White as is usual.
+    pub fn evaluations(&self) -> Vec<i32> {
+        self.reviews
+            .iter()
+            .map(|review| match review.player {
+                Color::White => review.score,
+                Color::Black => -review.score,
+            })
+            .collect()
+    }
+
+    /// The game with an evaluation after every move and the engine's choice
+    /// after every inaccuracy, mistake or blunder.
+    pub fn pgn(&self, tags: &[(&str, String)], result: &str) -> String {
+        let evaluations = self.evaluations();
+        annotated_pgn(tags, &self.moves(), result, |ply| {
+            let review = &self.reviews[ply];
+            let mut comment = format!("[%eval {:.2}]", evaluations[ply] as f64 / 100.0);
+            if let (Some(_), Some(better)) = (review.judgement, &review.better) {
+                comment.push_str(&format!(" {} was better.", better));
+            }
+            Annotation {
+                nag: review.judgement.map_or("", Judgement::nag),
+                comment: Some(comment),
+            }
+        })
+    }
+}
+
+#[test]
+fn finds_a_blunder() {
+    use super::clock::TimeControl;
+
+    let mut game_state = GameState::start(TimeControl::Unlimited);
+    // White's queen steps where Black's takes it for nothing.
+    for mv in ["e2e3", "e7e6", "d1g4", "d8e7", "g4g5"] {
+        game_state = game_state
+            .play(Move::from_uci(mv).unwrap())
+            .unwrap()
+            .switch_player();
+    }
+    let options = SearchOptions {
+        threads: 1,
+        depth: 3,
+        movetime: None,
+        ..Default::default()
+    };
+    let mut calls = 0;
+    let analysis = analyze(&game_state, options, |_, _| calls += 1);
+    assert_eq!(calls, 6);
+    assert_eq!(analysis.reviews.len(), 5);
+    assert!(analysis.reviews.iter().all(|review| review.loss >= 0));
+    let blunder = &analysis.reviews[4];
+    assert_eq!(blunder.player, Color::White);
+    assert_eq!(blunder.judgement, Some(Judgement::Blunder));
+    assert!(blunder.better.is_some());
+    // The first move is sound.
+    assert_eq!(analysis.reviews[0].judgement, None);
+    let accuracy = analysis.accuracy(Color::White);
+    assert!((0.0..=100.0).contains(&accuracy));
+
+    let pgn = analysis.pgn(&[], "*");
+    assert!(pgn.starts_with("\n1. e3 {[%eval "));
+    assert!(analysis
+        .summary(|color| color.to_string())
+        .contains("Black: accuracy "));
+}
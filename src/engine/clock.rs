@@ -0,0 +1,395 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use super::Color;
+
+/// How a player's time is topped up (or held back) around each move.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Increment {
+    None,
+    /// Fixed amount added after every move.
+    Fischer(Duration),
+    /// Time used is given back after the move, up to the delay.
+    Bronstein(Duration),
+    /// The clock only starts counting down once the delay has passed.
+    Delay(Duration),
+}
+
+/// A single period of a time control, e.g. "40 moves in 90 minutes".
+/// `moves: None` is a sudden death period lasting for the rest of the game.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TimeStage {
+    pub moves: Option<u32>,
+    pub base: Duration,
+    pub increment: Increment,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TimeControl {
+    Unlimited,
+    Staged(Vec<TimeStage>),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Side {
+    remaining: Duration,
+    stage: usize,
+    moves_in_stage: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Clock {
+    stages: Vec<TimeStage>,
+    sides: [Side; 2],
+    running: Option<(Color, Instant)>,
+    flagged: Option<Color>,
+}
+
+fn slot(player: Color) -> usize {
+    match player {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Option<Self> {
+        match time_control {
+            TimeControl::Unlimited => None,
+            TimeControl::Staged(stages) => {
+                let side = Side {
+                    remaining: stages[0].base,
+                    stage: 0,
+                    moves_in_stage: 0,
+                };
+                Some(Clock {
+                    stages,
+                    sides: [side; 2],
+                    running: None,
+                    flagged: None,
+                })
+            }
+        }
+    }
+
+    /// A clock for a game carried on after each side made `moves` moves,
+    /// with `remaining` time for White and Black.
+    pub fn resume(
+        time_control: TimeControl,
+        moves: [u32; 2],
+        remaining: [Duration; 2],
+    ) -> Option<Self> {
+        let mut clock = Clock::new(time_control)?;
+        for (side, moves) in clock.sides.iter_mut().zip(moves) {
+            for _ in 0..moves {
+                count_move(&clock.stages, side);
+            }
+        }
+        clock.sync(remaining[0], remaining[1]);
+        Some(clock)
+    }
+
+    /// The time control the clock was set up with.
+    pub fn time_control(&self) -> TimeControl {
+        TimeControl::Staged(self.stages.clone())
+    }
+
+    pub fn start(&mut self, player: Color) {
+        self.start_at(player, Instant::now())
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_at(Instant::now())
+    }
+
+    /// Stops the clock without counting a move, e.g. to save the game.
+    pub fn pause(&mut self) {
+        let Some((player, started)) = self.running.take() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        let increment = self.stage_of(player).increment;
+        let side = &mut self.sides[slot(player)];
+        side.remaining = side.remaining.saturating_sub(charged(increment, elapsed));
+        if side.remaining.is_zero() {
+            self.flagged = Some(player);
+        }
+    }
+
+    pub fn remaining(&self, player: Color) -> Duration {
+        self.remaining_at(player, Instant::now())
+    }
+
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(player, _)| player)
+    }
+
+    pub fn flagged(&self) -> Option<Color> {
+        self.flagged
+    }
+
+    /// The player whose clock is running and has run out, before the clock
+    /// is stopped and the flag counted.
+    pub fn out_of_time(&self) -> Option<Color> {
+        self.running()
+            .filter(|&player| self.remaining(player).is_zero())
+    }
+
+    /// Sets both clocks to the times reported by the other end of a
+    /// network game.
+    pub fn sync(&mut self, white: Duration, black: Duration) {
+        self.sides[slot(Color::White)].remaining = white;
+        self.sides[slot(Color::Black)].remaining = black;
+        self.flagged = [Color::White, Color::Black]
+            .into_iter()
+            .find(|&player| self.sides[slot(player)].remaining.is_zero());
+    }
+
+    /// What `player` gets back per move. A delay counts in full, since that
+    /// is what it amounts to when moving quickly.
+    pub fn increment(&self, player: Color) -> Duration {
+        match self.stage_of(player).increment {
+            Increment::None => Duration::ZERO,
+            Increment::Fischer(bonus) => bonus,
+            Increment::Bronstein(delay) | Increment::Delay(delay) => delay,
+        }
+    }
+
+    /// Moves `player` still has to make before the next stage adds time.
+    pub fn moves_to_go(&self, player: Color) -> Option<u32> {
+        let side = self.sides[slot(player)];
+        self.stages[side.stage]
+            .moves
+            .map(|moves| moves - side.moves_in_stage)
+    }
+
+    fn start_at(&mut self, player: Color, now: Instant) {
+        self.running = Some((player, now));
+    }
+
+    fn stop_at(&mut self, now: Instant) {
+        let Some((player, started)) = self.running.take() else {
+            return;
+        };
+        let elapsed = now.saturating_duration_since(started);
+        let increment = self.stage_of(player).increment;
+        let side = &mut self.sides[slot(player)];
+
+        side.remaining = side.remaining.saturating_sub(charged(increment, elapsed));
+        if side.remaining.is_zero() {
+            self.flagged = Some(player);
+            return;
+        }
+        match increment {
+            Increment::Fischer(bonus) => side.remaining += bonus,
+            Increment::Bronstein(delay) => side.remaining += elapsed.min(delay),
+            Increment::None | Increment::Delay(_) => {}
+        }
+
+        if count_move(&self.stages, side) {
+            side.remaining += self.stages[side.stage].base;
+        }
+    }
+
+    fn remaining_at(&self, player: Color, now: Instant) -> Duration {
+        let remaining = self.sides[slot(player)].remaining;
+        match self.running {
+            Some((running, started)) if running == player => {
+                let elapsed = now.saturating_duration_since(started);
+                remaining.saturating_sub(charged(self.stage_of(player).increment, elapsed))
+            }
+            _ => remaining,
+        }
+    }
+
+    fn stage_of(&self, player: Color) -> TimeStage {
+        self.stages[self.sides[slot(player)].stage]
+    }
+}
+
+/// Counts a move for `side`, returning whether that began the next stage.
+fn count_move(stages: &[TimeStage], side: &mut Side) -> bool {
+    side.moves_in_stage += 1;
+    if stages[side.stage].moves != Some(side.moves_in_stage) {
+        return false;
+    }
+    // The last stage repeats when it has a move count of its own.
+    side.stage = (side.stage + 1).min(stages.len() - 1);
+    side.moves_in_stage = 0;
+    true
+}
+
+/// Time actually taken off the clock for a move that lasted `elapsed`.
+fn charged(increment: Increment, elapsed: Duration) -> Duration {
+    match increment {
+        Increment::Delay(delay) => elapsed.saturating_sub(delay),
+        _ => elapsed,
+    }
+}
+
+/// Parses time controls written as `[moves/]minutes[+|b|d seconds]`, with
+/// stages separated by `:`. For example `5+3` (Fischer), `15d5` (US delay),
+/// `10b5` (Bronstein) or `40/90+30:30+30`. An empty string means no clock.
+impl FromStr for TimeControl {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() || s.eq_ignore_ascii_case("none") {
+            return Ok(TimeControl::Unlimited);
+        }
+        s.split(':')
+            .map(TimeStage::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map(TimeControl::Staged)
+    }
+}
+
+/// Written the way it is parsed, so that it can be sent to another program.
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeControl::Unlimited => write!(f, "none"),
+            TimeControl::Staged(stages) => {
+                for (index, stage) in stages.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ":")?;
+                    }
+                    write!(f, "{}", stage)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for TimeStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(moves) = self.moves {
+            write!(f, "{}/", moves)?;
+        }
+        write!(f, "{}", self.base.as_secs_f64() / 60.0)?;
+        match self.increment {
+            Increment::None => Ok(()),
+            Increment::Fischer(bonus) => write!(f, "+{}", bonus.as_secs_f64()),
+            Increment::Bronstein(delay) => write!(f, "b{}", delay.as_secs_f64()),
+            Increment::Delay(delay) => write!(f, "d{}", delay.as_secs_f64()),
+        }
+    }
+}
+
+impl FromStr for TimeStage {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (moves, rest) = match s.split_once('/') {
+            Some((moves, rest)) => {
+                let moves = moves
+                    .trim()
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|moves| *moves > 0)
+                    .ok_or_else(|| format!("Invalid number of moves: {}", moves))?;
+                (Some(moves), rest)
+            }
+            None => (None, s),
+        };
+
+        let (base, increment) = match rest.find(['+', 'b', 'd']) {
+            Some(position) => {
+                let seconds = parse_seconds(&rest[position + 1..])?;
+                let increment = match &rest[position..position + 1] {
+                    "+" => Increment::Fischer(seconds),
+                    "b" => Increment::Bronstein(seconds),
+                    _ => Increment::Delay(seconds),
+                };
+                (&rest[..position], increment)
+            }
+            None => (rest, Increment::None),
+        };
+
+        let base = base
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|minutes| minutes.is_finite() && *minutes > 0.0)
+            .map(|minutes| Duration::from_secs_f64(minutes * 60.0))
+            .ok_or_else(|| format!("Invalid number of minutes: {}", base))?;
+
+        Ok(TimeStage {
+            moves,
+            base,
+            increment,
+        })
+    }
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    s.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| format!("Invalid number of seconds: {}", s))
+}
+
+#[test]
+fn time_control_parsing() {
+    assert_eq!(TimeControl::from_str("\n").unwrap(), TimeControl::Unlimited);
+    assert_eq!(
+        TimeControl::from_str("40/90+30:30+30\n").unwrap(),
+        TimeControl::Staged(vec![
+            TimeStage {
+                moves: Some(40),
+                base: Duration::from_secs(90 * 60),
+                increment: Increment::Fischer(Duration::from_secs(30)),
+            },
+            TimeStage {
+                moves: None,
+                base: Duration::from_secs(30 * 60),
+                increment: Increment::Fischer(Duration::from_secs(30)),
+            },
+        ])
+    );
+    assert!(TimeControl::from_str("5x3").is_err());
+    for written in ["none", "5+3", "0.5d2", "40/90+30:30b30"] {
+        assert_eq!(TimeControl::from_str(written).unwrap().to_string(), written);
+    }
+    assert!(TimeControl::from_str("0/5").is_err());
+}
+
+#[test]
+fn clock_increments() {
+    let seconds = Duration::from_secs;
+    let start = Instant::now();
+
+    let mut fischer = Clock::new(TimeControl::from_str("1+2").unwrap()).unwrap();
+    fischer.start_at(Color::White, start);
+    fischer.stop_at(start + seconds(10));
+    assert_eq!(fischer.remaining(Color::White), seconds(52));
+
+    let mut bronstein = Clock::new(TimeControl::from_str("1b5").unwrap()).unwrap();
+    bronstein.start_at(Color::White, start);
+    bronstein.stop_at(start + seconds(3));
+    assert_eq!(bronstein.remaining(Color::White), seconds(60));
+
+    let mut delay = Clock::new(TimeControl::from_str("1d5").unwrap()).unwrap();
+    delay.start_at(Color::White, start);
+    assert_eq!(
+        delay.remaining_at(Color::White, start + seconds(4)),
+        seconds(60)
+    );
+    delay.stop_at(start + seconds(8));
+    assert_eq!(delay.remaining(Color::White), seconds(57));
+
+    let mut staged = Clock::new(TimeControl::from_str("1/1:2").unwrap()).unwrap();
+    staged.start_at(Color::White, start);
+    staged.stop_at(start + seconds(30));
+    assert_eq!(staged.remaining(Color::White), seconds(150));
+
+    let mut flag = Clock::new(TimeControl::from_str("1+5").unwrap()).unwrap();
+    flag.start_at(Color::Black, start);
+    flag.stop_at(start + seconds(61));
+    assert_eq!(flag.flagged(), Some(Color::Black));
+}
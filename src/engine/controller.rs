@@ -0,0 +1,577 @@
+use std::{
+    collections::VecDeque,
+    str::FromStr,
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::Duration,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{
+    clock::Clock,
+    evaluation::piece_value,
+    game_state::GameState,
+    hint::{hint, name, Hint},
+    moves::{apply_move, legal_moves, san, Move},
+    search::{Engine, SearchResult},
+    selfplay::engine_setup,
+    Color,
+};
+use crate::{
+    board::{
+        chesspiece::{get_movements, ChessPieceType},
+        layout::BoardCoordinates,
+    },
+    interface::{
+        board_layout::{BoardView, DrawInTerminal, Highlights},
+        console::outln,
+        get_input,
+        tui::{self, Action, Prompt},
+        CommandInput, GeneralInput,
+    },
+};
+
+/// Decides the moves for one side of the board.
+pub trait PlayerController {
+    /// Returns the move to play, or `None` to resign.
+    fn choose_move(&mut self, game_state: &GameState) -> Option<Move>;
+
+    /// Whether a person is sitting at the terminal for this side.
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    /// Anything worth showing about the last move, like search statistics.
+    fn info(&self) -> Option<String> {
+        None
+    }
+
+    /// The name of whoever plays this side, if they gave one.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Hears the move the other side just made, and whether a draw offer
+    /// came with it.
+    fn opponent_moved(&mut self, _game_state: &GameState, _mv: Move, _draw_offered: bool) {}
+
+    /// Whether a draw offer goes along with the move just chosen.
+    fn offers_draw(&mut self) -> bool {
+        false
+    }
+
+    /// Answers the draw offer the other side made with its last move.
+    fn accepts_draw(&mut self, _game_state: &GameState) -> bool {
+        false
+    }
+
+    /// Hears the answer to a draw offer made by this side.
+    fn draw_answered(&mut self, _accepted: bool) {}
+
+    /// Hears that the game is over, see `game_state.outcome`.
+    fn game_over(&mut self, _game_state: &GameState) {}
+
+    /// Hears which side the board on the screen has at the bottom.
+    fn board_shown(&mut self, _bottom: Color) {}
+
+    /// Catches up with a saved game it plays `color` in, as if it had made
+    /// that side's moves so far itself.
+    fn resumed(&mut self, _game_state: &GameState, _color: Color) {}
+
+    /// Both clocks as they were when the last move was made elsewhere, e.g.
+    /// by the other end of a network game.
+    fn synced_clock(&mut self) -> Option<(Duration, Duration)> {
+        None
+    }
+
+    /// How to set this side up again when a saved game is resumed, the way
+    /// `--white` and `--black` take it, e.g. `human:Alice`. `None` if it
+    /// cannot be, like the other end of a network game.
+    fn setup(&self) -> Option<String> {
+        None
+    }
+
+    /// What the player asked for instead of a move, if `choose_move`
+    /// returned `None` without resigning.
+    fn request(&self) -> Option<Request> {
+        None
+    }
+}
+
+/// Something to do with the whole game rather than the board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// Write the game to this file and carry on.
+    Save(String),
+    /// Give up the game for the one saved in this file.
+    Load(String),
+    /// Stop the game where it is, e.g. at the end of a script.
+    Exit,
+}
+
+/// How a side is called in prompts and announcements, e.g. `Alice (White)`.
+pub fn player_label(name: Option<&str>, color: Color) -> String {
+    match name {
+        Some(name) => format!("{} ({})", name, color),
+        None => color.to_string(),
+    }
+}
+
+/// Asks at the terminal for the field to move from and the field to move to,
+/// with the cursor when the terminal UI is on. Instead of a field the player
+/// may type `resign`, `draw` to offer a draw with the move, `hint`, `chat`
+/// and a message, or `save` or `load` and a file name.
+#[derive(Default)]
+pub struct HumanController {
+    pub name: Option<String>,
+    /// Sends chat messages to the other side, if there is anyone to read them.
+    pub chat: Option<Chat>,
+    offers_draw: bool,
+    /// Where the cursor was left on the last move.
+    cursor: Option<BoardCoordinates>,
+    /// The side at the bottom of the board on the screen.
+    bottom: Color,
+    /// The hint for the position after this many moves, and how much of it
+    /// was shown.
+    hint: Option<(usize, Hint, usize)>,
+    request: Option<Request>,
+}
+
+pub type Chat = Box<dyn FnMut(&str)>;
+
+impl PlayerController for HumanController {
+    fn choose_move(&mut self, game_state: &GameState) -> Option<Move> {
+        self.request = None;
+        let title = format!(
+            "{} to move.",
+            player_label(self.name(), game_state.current_player)
+        );
+        if tui::enabled() {
+            return self.choose_with_cursor(game_state, &title);
+        }
+        outln!("{}", title);
+        self.choose_fields(game_state)
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn offers_draw(&mut self) -> bool {
+        std::mem::take(&mut self.offers_draw)
+    }
+
+    fn accepts_draw(&mut self, game_state: &GameState) -> bool {
+        // At the end of the input the offer is declined, and the game stops
+        // at the next prompt.
+        let answer: Option<String> = get_input(&format!(
+            "{} offers a draw. Accept? (yes/no)",
+            game_state.current_player
+        ));
+        answer.is_some_and(|answer| answer.trim().to_ascii_lowercase().starts_with('y'))
+    }
+
+    fn draw_answered(&mut self, accepted: bool) {
+        if !accepted {
+            outln!("Your draw offer was declined.");
+        }
+    }
+
+    fn setup(&self) -> Option<String> {
+        Some(match &self.name {
+            Some(name) => format!("human:{}", name),
+            None => "human".to_string(),
+        })
+    }
+
+    fn request(&self) -> Option<Request> {
+        self.request.clone()
+    }
+
+    fn board_shown(&mut self, bottom: Color) {
+        self.bottom = bottom;
+    }
+}
+
+/// Whether the player to move has run out of time while thinking, which
+/// loses the game as soon as the clock is stopped.
+fn out_of_time(game_state: &GameState) -> bool {
+    let out =
+        game_state.clock.as_ref().and_then(Clock::out_of_time) == Some(game_state.current_player);
+    if out {
+        outln!("Your time is up.");
+    }
+    out
+}
+
+impl HumanController {
+    pub fn new(name: Option<String>) -> Self {
+        HumanController {
+            name,
+            ..Default::default()
+        }
+    }
+
+    /// Shows a little more of the engine's suggestion each time: first which
+    /// piece to move, then where to and why. Returns the fields to mark and
+    /// what to say about them.
+    fn next_hint(&mut self, game_state: &GameState) -> (Vec<BoardCoordinates>, String) {
+        let plies = game_state.history.len();
+        if self.hint.as_ref().map(|(at, _, _)| *at) != Some(plies) {
+            outln!("Thinking about a hint...");
+            self.hint = hint(game_state).map(|hint| (plies, hint, 0));
+        }
+        let Some((_, hint, shown)) = self.hint.as_mut() else {
+            return (vec![], "There is nothing to suggest.".to_string());
+        };
+        *shown += 1;
+        let mv = hint.mv;
+        let piece = game_state.board.at(&mv.from).piece.unwrap().piece_type;
+        if *shown == 1 {
+            let text = format!(
+                "Hint: move the {} on {}. Ask again for more.",
+                name(piece),
+                mv.from
+            );
+            (vec![mv.from], text)
+        } else {
+            let text = format!("Hint: {} {}.", san(&game_state.board, &mv), hint.reason);
+            (vec![mv.from, mv.to], text)
+        }
+    }
+
+    /// `None` if the player resigns.
+    fn choose_with_cursor(&mut self, game_state: &GameState, title: &str) -> Option<Move> {
+        let mut prompt = Prompt {
+            title,
+            can_chat: self.chat.is_some(),
+            ..Default::default()
+        };
+        loop {
+            if self.offers_draw && prompt.note.is_empty() {
+                prompt.note = "Your draw offer will be made with your move.".to_string();
+            }
+            match tui::choose(game_state, self.bottom, &prompt, &mut self.cursor) {
+                Action::Move(mv) => return Some(mv),
+                Action::Resign | Action::OutOfTime => return None,
+                Action::OfferDraw => self.offers_draw = true,
+                Action::Chat(text) => {
+                    if let Some(chat) = self.chat.as_mut() {
+                        chat(&text);
+                    }
+                }
+                Action::Hint => (prompt.hint, prompt.note) = self.next_hint(game_state),
+                Action::Save(path) => {
+                    self.request = Some(Request::Save(path));
+                    return None;
+                }
+                Action::Load(path) => {
+                    self.request = Some(Request::Load(path));
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// `None` if the player resigns.
+    fn choose_fields(&mut self, game_state: &GameState) -> Option<Move> {
+        let board = &game_state.board;
+        let legal = legal_moves(board, &game_state.current_player);
+        let mut selected: Option<BoardCoordinates> = None;
+        loop {
+            let input: Option<GeneralInput> = get_input(match selected {
+                None => "Select a field with your figure",
+                Some(_) => "Select a field to which you want to move your figure",
+            });
+            let Some(input) = input else {
+                self.request = Some(Request::Exit);
+                return None;
+            };
+            if out_of_time(game_state) {
+                return None;
+            }
+            let coordinates = match input {
+                GeneralInput::Coordinates(coordinates) => coordinates,
+                GeneralInput::Command(CommandInput::Back) => {
+                    selected = None;
+                    continue;
+                }
+                GeneralInput::Command(CommandInput::Resign) => return None,
+                GeneralInput::Command(CommandInput::Exit) => {
+                    self.request = Some(Request::Exit);
+                    return None;
+                }
+                GeneralInput::Command(CommandInput::Score) => {
+                    let material = |color| {
+                        board
+                            .get_all_fields_by_player(&color)
+                            .into_iter()
+                            .filter_map(|field| field.piece)
+                            .filter(|piece| piece.piece_type != ChessPieceType::King)
+                            .map(|piece| piece_value(piece.piece_type) / 100)
+                            .sum::<i32>()
+                    };
+                    outln!(
+                        "Material: White {}, Black {}",
+                        material(Color::White),
+                        material(Color::Black)
+                    );
+                    continue;
+                }
+                GeneralInput::Command(CommandInput::Draw) => {
+                    self.offers_draw = true;
+                    outln!("Your draw offer will be made with your move.");
+                    continue;
+                }
+                GeneralInput::Command(CommandInput::Chat(text)) => {
+                    match self.chat.as_mut() {
+                        Some(chat) => chat(&text),
+                        None => outln!("There is nobody to chat with."),
+                    }
+                    continue;
+                }
+                GeneralInput::Command(CommandInput::Save(path)) => {
+                    self.request = Some(Request::Save(path));
+                    return None;
+                }
+                GeneralInput::Command(CommandInput::Load(path)) => {
+                    self.request = Some(Request::Load(path));
+                    return None;
+                }
+                GeneralInput::Command(CommandInput::Hint) => {
+                    let (fields, text) = self.next_hint(game_state);
+                    BoardView {
+                        board,
+                        bottom: self.bottom,
+                        highlights: Highlights {
+                            hint: fields,
+                            ..Highlights::of(game_state)
+                        },
+                    }
+                    .draw();
+                    outln!("{}", text);
+                    continue;
+                }
+                _ => {
+                    outln!("Invalid, try again.");
+                    continue;
+                }
+            };
+            match selected {
+                None => {
+                    let select_field = board.at(&coordinates);
+                    if select_field.check_player() != Some(game_state.current_player) {
+                        outln!("This field doesn't have your figure.");
+                    } else if !legal.iter().any(|mv| mv.from == coordinates) {
+                        outln!("This figure has no possible moves!");
+                    } else {
+                        selected = Some(coordinates);
+                        BoardView {
+                            board,
+                            bottom: self.bottom,
+                            highlights: Highlights::of(game_state).selecting(selected, &legal),
+                        }
+                        .draw();
+                    }
+                }
+                Some(from) => {
+                    let to = coordinates;
+                    if legal.iter().any(|mv| mv.from == from && mv.to == to) {
+                        let piece = board.at(&from).piece.unwrap();
+                        let promotion = if piece.can_change(to) {
+                            let piece =
+                                get_input::<ChessPieceType>("Choose a piece to replace the pawn: ");
+                            if piece.is_none() {
+                                self.request = Some(Request::Exit);
+                                return None;
+                            }
+                            piece
+                        } else {
+                            None
+                        };
+                        let mv = Move {
+                            from,
+                            to,
+                            promotion,
+                        };
+                        if legal.contains(&mv) {
+                            return Some(mv);
+                        }
+                        outln!("The pawn can't be replaced with that piece.");
+                    } else if get_movements(&board.at(&from), board).contains(&to) {
+                        outln!("Invalid move, your King will be in check!")
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Plays any legal move, picked with `rng`.
+pub struct RandomController<R = StdRng> {
+    rng: R,
+}
+
+impl RandomController {
+    /// The same seed plays the same moves in the same game.
+    pub fn seeded(seed: u64) -> Self {
+        RandomController::new(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl<R: Rng> RandomController<R> {
+    pub fn new(rng: R) -> Self {
+        RandomController { rng }
+    }
+}
+
+impl<R: Rng> PlayerController for RandomController<R> {
+    fn choose_move(&mut self, game_state: &GameState) -> Option<Move> {
+        let moves = legal_moves(&game_state.board, &game_state.current_player);
+        (!moves.is_empty()).then(|| moves[self.rng.gen_range(0..moves.len())])
+    }
+
+    fn resumed(&mut self, game_state: &GameState, color: Color) {
+        // Draws as many numbers from the same ranges as choosing those moves
+        // did, so that the game goes on the way it would have without a save.
+        let mut board = game_state.start_board.clone();
+        let mut player = game_state.start_player;
+        for mv in &game_state.history {
+            if player == color {
+                self.rng.gen_range(0..legal_moves(&board, &player).len());
+            }
+            board = apply_move(board, mv);
+            player = player.switch();
+        }
+    }
+
+    fn setup(&self) -> Option<String> {
+        Some("random".to_string())
+    }
+}
+
+pub struct EngineController {
+    engine: Engine,
+    last_search: Option<SearchResult>,
+}
+
+impl EngineController {
+    pub fn new(engine: Engine) -> Self {
+        EngineController {
+            engine,
+            last_search: None,
+        }
+    }
+}
+
+impl PlayerController for EngineController {
+    fn choose_move(&mut self, game_state: &GameState) -> Option<Move> {
+        // Never think longer than a twentieth of what is left on the clock.
+        let budget = game_state
+            .clock
+            .as_ref()
+            .map(|clock| clock.remaining(game_state.current_player) / 20);
+        let (searching, finished) = mpsc::channel::<()>();
+        let stop = self.engine.stop_handle();
+        stop.store(false, Ordering::Relaxed);
+        let watchdog = budget.map(|budget| {
+            thread::spawn(move || {
+                if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(budget) {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            })
+        });
+
+        let result = self
+            .engine
+            .search(&game_state.board, game_state.current_player);
+        drop(searching);
+        if let Some(watchdog) = watchdog {
+            let _ = watchdog.join();
+        }
+        self.last_search = Some(result);
+        result.best_move
+    }
+
+    fn info(&self) -> Option<String> {
+        self.last_search
+            .map(|search| format!("last search: {}", search))
+    }
+
+    fn setup(&self) -> Option<String> {
+        Some(engine_setup(&self.engine.options))
+    }
+}
+
+/// Plays a fixed list of moves, then resigns.
+pub struct ScriptedController {
+    moves: VecDeque<Move>,
+}
+
+impl ScriptedController {
+    pub fn new(moves: impl IntoIterator<Item = Move>) -> Self {
+        ScriptedController {
+            moves: moves.into_iter().collect(),
+        }
+    }
+}
+
+/// Moves separated by whitespace, e.g. `e2e3 d1h5`.
+impl FromStr for ScriptedController {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_whitespace()
+            .map(Move::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map(ScriptedController::new)
+    }
+}
+
+impl PlayerController for ScriptedController {
+    fn choose_move(&mut self, _game_state: &GameState) -> Option<Move> {
+        self.moves.pop_front()
+    }
+}
+
+#[test]
+fn seeded_random_moves() {
+    use super::{clock::TimeControl, game_state::Players};
+
+    let play = |seed| {
+        let mut players = Players {
+            white: Box::new(RandomController::seeded(seed)),
+            black: Box::new(RandomController::seeded(seed + 1)),
+        };
+        let mut game_state = GameState::start(TimeControl::Unlimited);
+        for _ in 0..10 {
+            game_state = game_state.do_move(&mut players).switch_player();
+        }
+        game_state.history
+    };
+    assert_eq!(play(7), play(7));
+    assert_ne!(play(7), play(8));
+
+    // Resumed after four moves, the same seeds play the rest the same way.
+    let mut game_state = GameState::start(TimeControl::Unlimited);
+    for mv in &play(7)[..4] {
+        game_state = game_state.play(*mv).unwrap().switch_player();
+    }
+    let mut players = Players {
+        white: Box::new(RandomController::seeded(7)),
+        black: Box::new(RandomController::seeded(8)),
+    };
+    players.white.resumed(&game_state, Color::White);
+    players.black.resumed(&game_state, Color::Black);
+    for _ in 4..10 {
+        game_state = game_state.do_move(&mut players).switch_player();
+    }
+    assert_eq!(game_state.history, play(7));
+}
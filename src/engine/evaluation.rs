@@ -0,0 +1,122 @@
+use std::{fmt, str::FromStr};
+
+use super::Color;
+use crate::board::{
+    chesspiece::{ChessPiece, ChessPieceType},
+    layout::{Board, BoardCoordinates},
+};
+
+pub const MATE: i32 = 30_000;
+
+pub fn piece_value(piece_type: ChessPieceType) -> i32 {
+    match piece_type {
+        ChessPieceType::Pawn => 100,
+        ChessPieceType::Knight => 320,
+        ChessPieceType::Bishop => 330,
+        ChessPieceType::Rook => 500,
+        ChessPieceType::Queen => 900,
+        ChessPieceType::King => 0,
+    }
+}
+
+/// How much each part of the evaluation counts, in percent.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Weights {
+    pub material: i32,
+    pub positional: i32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            material: 100,
+            positional: 100,
+        }
+    }
+}
+
+/// Material and positional percentages separated by a slash, e.g. `100/50`.
+impl FromStr for Weights {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (material, positional) = s
+            .split_once('/')
+            .ok_or_else(|| format!("Weights must look like 100/50: {}", s))?;
+        let parse = |weight: &str| {
+            weight
+                .trim()
+                .parse::<i32>()
+                .map_err(|_| format!("Invalid weight: {}", weight))
+        };
+        Ok(Weights {
+            material: parse(material)?,
+            positional: parse(positional)?,
+        })
+    }
+}
+
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.material, self.positional)
+    }
+}
+
+/// Static evaluation in centipawns from the point of view of `player`.
+pub fn evaluate(board: &Board, player: &Color, weights: &Weights) -> i32 {
+    board
+        .0
+        .iter()
+        .filter_map(|field| field.piece.map(|piece| (piece, field.coordinates)))
+        .map(|(piece, coordinates)| {
+            let score = (piece_value(piece.piece_type) * weights.material
+                + positional(&piece, coordinates) * weights.positional)
+                / 100;
+            if piece.player == *player {
+                score
+            } else {
+                -score
+            }
+        })
+        .sum()
+}
+
+fn positional(piece: &ChessPiece, coordinates: BoardCoordinates) -> i32 {
+    let (row, column) = (
+        coordinates.index() as i32 / 8,
+        coordinates.index() as i32 % 8,
+    );
+    // 0 in the corners, 12 on the four central fields.
+    let centrality = 14 - (2 * row - 7).abs() - (2 * column - 7).abs();
+    match piece.piece_type {
+        ChessPieceType::Pawn => {
+            let advanced = match piece.player {
+                Color::White => 6 - row,
+                Color::Black => row - 1,
+            };
+            advanced * 8 + centrality
+        }
+        ChessPieceType::Knight | ChessPieceType::Bishop => centrality * 3,
+        ChessPieceType::Rook => 0,
+        ChessPieceType::Queen => centrality,
+        ChessPieceType::King => -centrality * 2,
+    }
+}
+
+#[test]
+fn starting_position_is_balanced() {
+    let board = Board::default().fill_standard_pieces();
+    assert_eq!(evaluate(&board, &Color::White, &Weights::default()), 0);
+    let board = board.remove_piece(BoardCoordinates::from_index(3).unwrap());
+    assert!(evaluate(&board, &Color::White, &Weights::default()) > 800);
+}
+
+#[test]
+fn weights_parsing() {
+    let weights: Weights = "100/0".parse().unwrap();
+    assert_eq!(weights.positional, 0);
+    assert!("100".parse::<Weights>().is_err());
+    let board = Board::default()
+        .fill_standard_pieces()
+        .remove_piece(BoardCoordinates::from_index(8).unwrap());
+    assert_eq!(evaluate(&board, &Color::White, &weights), 100);
+}
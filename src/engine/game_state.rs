@@ -1,161 +1,227 @@
-use super::Player;
+use super::{
+    clock::{Clock, TimeControl},
+    controller::{player_label, PlayerController},
+    moves::{apply_move, legal_moves, Move},
+    Color, Outcome,
+};
 use crate::{
-    board::{
-        chesspiece::{get_movements, ChessPieceType},
-        field::Field,
-        layout::{Board, BoardCoordinates},
-    },
-    interface::{get_input, CommandInput, GeneralInput},
+    board::{chesspiece::get_movements, chesspiece::ChessPieceType, fen::to_fen, layout::Board},
+    interface::clock_layout::ClockTicker,
 };
 
-use rand::Rng;
-
+#[derive(Clone)]
 pub struct GameState {
-    pub current_player: Player,
+    pub current_player: Color,
     pub board: Board,
-    pub checkmate: bool,
+    pub clock: Option<Clock>,
+    pub outcome: Option<Outcome>,
+    /// The position the game started from, usually the standard one.
+    pub start_board: Board,
+    pub start_player: Color,
+    /// The number of the first move, e.g. from the FEN the game was set up
+    /// from.
+    pub first_move: usize,
+    /// Every move played so far, starting from `start_board`.
+    pub history: Vec<Move>,
+    /// The seed of the random choices, to play the game again the same way.
+    pub seed: Option<u64>,
 }
 
-impl GameState {
-    pub fn start() -> Self {
-        GameState {
-            current_player: Player::User,
-            board: Board::default().fill_standard_pieces(),
-            checkmate: false,
+/// Who makes the moves for each colour.
+pub struct Players {
+    pub white: Box<dyn PlayerController>,
+    pub black: Box<dyn PlayerController>,
+}
+
+impl Players {
+    pub fn get(&self, color: Color) -> &dyn PlayerController {
+        match color {
+            Color::White => self.white.as_ref(),
+            Color::Black => self.black.as_ref(),
         }
     }
 
-    pub fn do_move(self) -> Self {
-        match self.current_player {
-            Player::User => do_user_move(self),
-            Player::Opponent => do_computer_move(self),
+    pub fn get_mut(&mut self, color: Color) -> &mut dyn PlayerController {
+        match color {
+            Color::White => self.white.as_mut(),
+            Color::Black => self.black.as_mut(),
         }
     }
 
-    pub fn switch_player(self) -> Self {
-        Self {
-            current_player: self.current_player.switch(),
-            ..self
-        }
+    pub fn label(&self, color: Color) -> String {
+        player_label(self.get(color).name(), color)
     }
 }
 
-fn do_user_move(mut game_state: GameState) -> GameState {
-    let king_in_check = check_if_king_in_check(&game_state.board, &game_state.current_player);
+impl GameState {
+    pub fn start(time_control: TimeControl) -> Self {
+        Self::from_position(
+            Board::default().fill_standard_pieces(),
+            Color::White,
+            time_control,
+        )
+    }
 
-    if king_in_check && check_checkmate(game_state.board.clone(), &game_state.current_player) {
-        game_state.checkmate = true;
-        return game_state;
+    pub fn from_position(board: Board, player: Color, time_control: TimeControl) -> Self {
+        GameState {
+            current_player: player,
+            board: board.clone(),
+            clock: Clock::new(time_control),
+            outcome: None,
+            start_board: board,
+            start_player: player,
+            first_move: 1,
+            history: vec![],
+            seed: None,
+        }
     }
 
-    let (selected_field, selected_destination_field) = choose_fields(&game_state);
-    game_state.board = move_piece(
-        game_state.board,
-        &selected_field,
-        &selected_destination_field,
-    );
-    game_state
-}
+    /// Takes back the last move by replaying the others. The clocks keep
+    /// running as they are.
+    pub fn undo(mut self) -> Self {
+        self.history.pop();
+        let mut board = self.start_board.clone();
+        let mut player = self.start_player;
+        for mv in &self.history {
+            board = apply_move(board, mv);
+            player = player.switch();
+        }
+        GameState {
+            current_player: player,
+            board,
+            outcome: None,
+            ..self
+        }
+    }
 
-fn choose_fields(game_state: &GameState) -> (Field, Field) {
-    let mut selected_field: Option<Field> = None;
-    let mut selected_destination_field: Option<Field> = None;
-    let mut possible_fields: Vec<BoardCoordinates> = vec![];
-    loop {
-        match (selected_field, selected_destination_field) {
-            (None, None) => {
-                let selected_field_coordinates: BoardCoordinates =
-                    get_input("Select a field with your figure");
-                let select_field = game_state.board.at(&selected_field_coordinates);
-                if select_field.check_player() != Some(game_state.current_player) {
-                    println!("This field doesn't have your figure.");
-                } else {
-                    possible_fields = get_movements(&select_field, &game_state.board);
-                    if possible_fields.is_empty() {
-                        println!("This figure has no possible moves!");
-                    } else {
-                        selected_field = Some(select_field);
-                    }
-                }
+    /// Plays `mv` for the current player without asking anyone, e.g. to set
+    /// up an opening.
+    pub fn play(mut self, mv: Move) -> Result<Self, String> {
+        if !legal_moves(&self.board, &self.current_player).contains(&mv) {
+            return Err(format!("Illegal move: {}", mv));
+        }
+        self.board = apply_move(self.board, &mv);
+        self.history.push(mv);
+        Ok(self)
+    }
+
+    pub fn do_move(mut self, players: &mut Players) -> Self {
+        let player = self.current_player;
+        if let Some(outcome) = self.outcome_without_moves() {
+            self.outcome = Some(outcome);
+            return self.announce(players);
+        }
+        let moves = legal_moves(&self.board, &player);
+
+        if let Some(clock) = self.clock.as_mut() {
+            clock.start(player);
+        }
+        let controller = players.get_mut(player);
+        let chosen = {
+            let _ticker = self
+                .clock
+                .clone()
+                .filter(|_| controller.is_interactive())
+                .map(ClockTicker::spawn);
+            controller.choose_move(&self)
+        };
+        if chosen.is_none() && controller.request().is_some() {
+            // Nothing happens on the board; the caller sees to the request.
+            if let Some(clock) = self.clock.as_mut() {
+                clock.pause();
             }
-            (Some(select_field), None) => {
-                let input: GeneralInput =
-                    get_input("Select a field to which you want to move your figure");
-                if let GeneralInput::Command(CommandInput::Back) = input {
-                    selected_field = None;
-                    continue;
-                }
-                if let GeneralInput::Coordinates(coordinates) = input {
-                    if possible_fields.contains(&coordinates) {
-                        let destination_field = game_state.board.at(&coordinates);
-                        if is_not_checked_after_move(
-                            game_state.board.clone(),
-                            &select_field,
-                            &destination_field,
-                        ) {
-                            selected_destination_field = Some(destination_field);
-                        } else {
-                            println!("Invalid move, your King will be in check!")
-                        }
+            return self;
+        }
+        if let Some(clock) = self.clock.as_mut() {
+            clock.stop();
+            if let Some((white, black)) = controller.synced_clock() {
+                clock.sync(white, black);
+            }
+        }
+
+        let winner = player.switch();
+        // A move made after the flag fell does not count.
+        if self.clock.as_ref().and_then(Clock::flagged) == Some(player) {
+            self.outcome = Some(if self.board.has_mating_material(&winner) {
+                Outcome::TimeForfeit { winner }
+            } else {
+                Outcome::TimeoutVsInsufficientMaterial
+            });
+            return self.announce(players);
+        }
+        match chosen {
+            Some(chosen) if moves.contains(&chosen) => {
+                self.board = apply_move(self.board, &chosen);
+                self.history.push(chosen);
+                let offered = controller.offers_draw();
+                let opponent = players.get_mut(winner);
+                opponent.opponent_moved(&self, chosen, offered);
+                if offered {
+                    let accepted = opponent.accepts_draw(&self);
+                    players.get_mut(player).draw_answered(accepted);
+                    if accepted {
+                        self.outcome = Some(Outcome::DrawAgreed);
+                        return self.announce(players);
                     }
                 }
             }
-            (Some(select_field), Some(destination_field)) => {
-                break (select_field, destination_field)
+            Some(_) => {
+                self.outcome = Some(Outcome::IllegalMove { winner });
+                return self.announce(players);
             }
-            _ => {
-                // This should never happen
-                (selected_field, selected_destination_field) = (None, None);
+            None => {
+                self.outcome = Some(Outcome::Resignation { winner });
+                return self.announce(players);
             }
         }
+        self
     }
-}
 
-fn is_not_checked_after_move(
-    board: Board,
-    selected_field: &Field,
-    selected_destination_field: &Field,
-) -> bool {
-    let board = move_piece(board, selected_field, selected_destination_field);
-    let player = selected_field.check_player().unwrap();
-    !check_if_king_in_check(&board, &player)
-}
+    /// Lets both sides know that the game is over.
+    fn announce(self, players: &mut Players) -> Self {
+        players.white.game_over(&self);
+        players.black.game_over(&self);
+        self
+    }
 
-fn do_computer_move(mut game_state: GameState) -> GameState {
-    let king_in_check = check_if_king_in_check(&game_state.board, &game_state.current_player);
+    /// Checkmate or stalemate if the current player cannot move.
+    pub fn outcome_without_moves(&self) -> Option<Outcome> {
+        let player = self.current_player;
+        if !legal_moves(&self.board, &player).is_empty() {
+            None
+        } else if check_if_king_in_check(&self.board, &player) {
+            Some(Outcome::Checkmate {
+                winner: player.switch(),
+            })
+        } else {
+            Some(Outcome::Stalemate)
+        }
+    }
 
-    if king_in_check && check_checkmate(game_state.board.clone(), &game_state.current_player) {
-        game_state.checkmate = true;
-        return game_state;
+    /// The current position, counting full moves from the start of the game.
+    pub fn fen(&self) -> String {
+        let plies = self.history.len() + usize::from(self.start_player == Color::Black);
+        to_fen(
+            &self.board,
+            self.current_player,
+            self.first_move + plies / 2,
+        )
     }
 
-    let possible_fields = game_state
-        .board
-        .get_all_fields_by_player(&game_state.current_player);
-
-    let (selected_field, destination_field) = loop {
-        let selected_field =
-            possible_fields[rand::thread_rng().gen_range(0..possible_fields.len())];
-        let possible_destinations = get_movements(&selected_field, &game_state.board);
-        if !possible_destinations.is_empty() {
-            let destination =
-                possible_destinations[rand::thread_rng().gen_range(0..possible_destinations.len())];
-            let destination_field = game_state.board.at(&destination);
-            if is_not_checked_after_move(
-                game_state.board.clone(),
-                &selected_field,
-                &destination_field,
-            ) {
-                break (selected_field, destination_field);
-            }
-        }
-    };
-    game_state.board = move_piece(game_state.board, &selected_field, &destination_field);
+    /// The FEN of the position the game started from.
+    pub fn start_fen(&self) -> String {
+        to_fen(&self.start_board, self.start_player, self.first_move)
+    }
 
-    game_state
+    pub fn switch_player(self) -> Self {
+        Self {
+            current_player: self.current_player.switch(),
+            ..self
+        }
+    }
 }
 
-fn check_if_king_in_check(board: &Board, current_player: &Player) -> bool {
+pub(crate) fn check_if_king_in_check(board: &Board, current_player: &Color) -> bool {
     let fields = board.get_all_fields_by_player(&current_player.switch());
 
     for field in fields {
@@ -170,22 +236,76 @@ fn check_if_king_in_check(board: &Board, current_player: &Player) -> bool {
     false
 }
 
-fn move_piece(mut board: Board, selected: &Field, destination: &Field) -> Board {
-    let piece = selected.piece.unwrap();
-    board = board.remove_piece(selected.coordinates);
-    board = board.add_replace_piece(destination.coordinates, piece);
-    board
+#[test]
+fn scripted_players() {
+    use super::controller::ScriptedController;
+
+    let script = |moves: &str| Box::new(moves.parse::<ScriptedController>().unwrap());
+    let mut players = Players {
+        white: script("e2e3"),
+        black: script(""),
+    };
+    let game_state = GameState::start(TimeControl::Unlimited).do_move(&mut players);
+    assert_eq!(game_state.outcome, None);
+    assert_eq!(
+        game_state.board.at(&"e3".parse().unwrap()).check_player(),
+        Some(Color::White)
+    );
+    let game_state = game_state.switch_player().do_move(&mut players);
+    assert_eq!(
+        game_state.outcome,
+        Some(Outcome::Resignation {
+            winner: Color::White
+        })
+    );
+
+    let game_state = game_state.undo();
+    assert_eq!(game_state.outcome, None);
+    assert_eq!(
+        crate::board::fen::to_fen(&game_state.board, game_state.current_player, 1),
+        crate::board::fen::STARTING_FEN
+    );
+
+    let mut players = Players {
+        white: script("e2e4"),
+        black: script(""),
+    };
+    let game_state = GameState::start(TimeControl::Unlimited).do_move(&mut players);
+    assert_eq!(
+        game_state.outcome,
+        Some(Outcome::IllegalMove {
+            winner: Color::Black
+        })
+    );
 }
 
-fn check_checkmate(board: Board, current_player: &Player) -> bool {
-    let fields = board.get_all_fields_by_player(current_player);
-    for field in fields {
-        for destination_coordinates in get_movements(&field, &board) {
-            let temp_board = move_piece(board.clone(), &field, &board.at(&destination_coordinates));
-            if !check_if_king_in_check(&temp_board, current_player) {
-                return false;
-            }
-        }
-    }
-    true
+#[test]
+fn running_out_of_time_while_thinking() {
+    use super::controller::HumanController;
+    use crate::interface::console::capture;
+    use std::time::Duration;
+
+    let mut game_state = GameState::start(TimeControl::Unlimited);
+    game_state.clock = Clock::resume(
+        "5+0".parse().unwrap(),
+        [0, 0],
+        [Duration::ZERO, Duration::from_secs(300)],
+    );
+    let mut players = Players {
+        white: Box::new(HumanController::new(None)),
+        black: Box::new(HumanController::new(None)),
+    };
+    let mut played = None;
+    let output = capture("e2\ne3\n", || {
+        played = Some(game_state.do_move(&mut players))
+    });
+    let game_state = played.unwrap();
+    assert!(output.ends_with("e2\nYour time is up.\n"));
+    assert_eq!(
+        game_state.outcome,
+        Some(Outcome::TimeForfeit {
+            winner: Color::Black
+        })
+    );
+    assert!(game_state.history.is_empty());
 }
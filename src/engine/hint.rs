@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use super::{
+    evaluation::{piece_value, MATE},
+    game_state::{check_if_king_in_check, GameState},
+    moves::{apply_move, legal_moves, Move},
+    search::{Engine, SearchOptions},
+    Color,
+};
+use crate::board::{
+    chesspiece::{get_movements, ChessPiece, ChessPieceType},
+    layout::{Board, BoardCoordinates},
+};
+
+/// How long the engine thinks about a hint.
+const HINT_TIME: Duration = Duration::from_millis(1000);
+
+/// A move suggested by the engine, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hint {
+    pub mv: Move,
+    /// A few words like "wins material" or "defends the hanging knight".
+    pub reason: String,
+}
+
+/// Asks the engine for the best move at a short time limit. `None` if
+/// there is no move to make.
+pub fn hint(game_state: &GameState) -> Option<Hint> {
+    let engine = Engine::new(SearchOptions {
+        depth: 8,
+        movetime: Some(HINT_TIME),
+        ..Default::default()
+    });
+    let player = game_state.current_player;
+    let result = engine.search(&game_state.board, player);
+    let mv = result.best_move?;
+    Some(Hint {
+        mv,
+        reason: explain(&game_state.board, player, mv, result.score),
+    })
+}
+
+/// The piece's name in lower case, e.g. `knight`.
+pub fn name(piece_type: ChessPieceType) -> &'static str {
+    match piece_type {
+        ChessPieceType::Pawn => "pawn",
+        ChessPieceType::Bishop => "bishop",
+        ChessPieceType::Knight => "knight",
+        ChessPieceType::Rook => "rook",
+        ChessPieceType::Queen => "queen",
+        ChessPieceType::King => "king",
+    }
+}
+
+/// The pieces of `by` that could take on `field`.
+fn attackers(board: &Board, field: BoardCoordinates, by: Color) -> Vec<ChessPiece> {
+    // Pawns only take what is there, so put something of the other side on
+    // the field first.
+    let probe = board.clone().add_replace_piece(
+        field,
+        ChessPiece {
+            piece_type: ChessPieceType::Pawn,
+            player: by.switch(),
+        },
+    );
+    probe
+        .get_all_fields_by_player(&by)
+        .into_iter()
+        .filter(|attacker| get_movements(attacker, &probe).contains(&field))
+        .filter_map(|attacker| attacker.piece)
+        .collect()
+}
+
+/// Pieces of `player` that can be taken for less than they are worth.
+fn hanging(board: &Board, player: Color) -> Vec<(BoardCoordinates, ChessPieceType)> {
+    board
+        .get_all_fields_by_player(&player)
+        .into_iter()
+        .filter_map(|field| Some((field.coordinates, field.piece?.piece_type)))
+        .filter(|(_, piece_type)| *piece_type != ChessPieceType::King)
+        .filter(|(field, piece_type)| {
+            let threats = attackers(board, *field, player.switch());
+            let cheapest = threats
+                .iter()
+                .map(|attacker| piece_value(attacker.piece_type))
+                .min();
+            match cheapest {
+                None => false,
+                Some(cheapest) if cheapest < piece_value(*piece_type) => true,
+                Some(_) => attackers(board, *field, player).is_empty(),
+            }
+        })
+        .collect()
+}
+
+fn is_mate(board: &Board, player: Color) -> bool {
+    legal_moves(board, &player).is_empty() && check_if_king_in_check(board, &player)
+}
+
+/// Why `mv` is a good move for `player`, judged from the position after it
+/// and the engine's `score`.
+pub fn explain(board: &Board, player: Color, mv: Move, score: i32) -> String {
+    let opponent = player.switch();
+    let after = apply_move(board.clone(), &mv);
+    let piece = board.at(&mv.from).piece.map(|piece| piece.piece_type);
+
+    if is_mate(&after, opponent) {
+        return "checkmates".to_string();
+    }
+    if score >= MATE - 100 {
+        return "leads to mate".to_string();
+    }
+    if let Some(promotion) = mv.promotion {
+        return format!("promotes to a {}", name(promotion));
+    }
+    if let Some(taken) = board.at(&mv.to).piece {
+        let defended = !attackers(&after, mv.to, opponent).is_empty();
+        let mover = piece.map_or(0, piece_value);
+        return if !defended || piece_value(taken.piece_type) > mover {
+            format!("wins material by taking the {}", name(taken.piece_type))
+        } else {
+            format!("trades for the {}", name(taken.piece_type))
+        };
+    }
+    let still_hanging = hanging(&after, player);
+    let saved = hanging(board, player).into_iter().find(|(field, _)| {
+        let now = if *field == mv.from { mv.to } else { *field };
+        !still_hanging.iter().any(|(other, _)| *other == now)
+    });
+    if let Some((field, piece_type)) = saved {
+        return if field == mv.from {
+            format!("saves the hanging {}", name(piece_type))
+        } else {
+            format!("defends the hanging {}", name(piece_type))
+        };
+    }
+    // If the opponent did nothing now, could we mate?
+    let threatens_mate = legal_moves(&after, &player)
+        .iter()
+        .any(|threat| is_mate(&apply_move(after.clone(), threat), opponent));
+    if threatens_mate {
+        return "threatens mate".to_string();
+    }
+    if check_if_king_in_check(&after, &opponent) {
+        return "gives check".to_string();
+    }
+    if let Some((_, piece_type)) = hanging(&after, opponent).first() {
+        return format!("attacks the {}", name(*piece_type));
+    }
+    "improves the position".to_string()
+}
+
+#[test]
+fn explains_moves() {
+    use crate::board::fen::from_fen;
+
+    let reason = |fen: &str, mv: &str| {
+        let (board, player) = from_fen(fen).unwrap();
+        explain(&board, player, Move::from_uci(mv).unwrap(), 0)
+    };
+    // A rook takes an undefended knight.
+    assert_eq!(
+        reason("n3k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"),
+        "wins material by taking the knight"
+    );
+    // Back-rank mate.
+    assert_eq!(
+        reason("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"),
+        "checkmates"
+    );
+    // The rook gets to the open file and threatens mate on the back rank.
+    assert_eq!(
+        reason("6k1/5ppp/8/8/8/7R/8/4K3 w - - 0 1", "h3a3"),
+        "threatens mate"
+    );
+    // The knight on e5 is attacked by a pawn and steps away.
+    assert_eq!(
+        reason("4k3/8/3p4/4N3/8/8/8/4K3 w - - 0 1", "e5c4"),
+        "saves the hanging knight"
+    );
+}
@@ -1,26 +1,144 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
+pub mod analysis;
+pub mod clock;
+pub mod controller;
+pub mod evaluation;
 pub mod game_state;
-#[derive(Eq, PartialEq, Debug, Clone, Copy)]
-pub enum Player {
-    User,
-    Opponent,
+pub mod hint;
+pub mod moves;
+pub mod pgn;
+pub mod remote;
+pub mod save;
+pub mod search;
+pub mod selfplay;
+pub mod server;
+pub mod skill;
+pub mod sprt;
+pub mod transposition;
+pub mod uci;
+pub mod xboard;
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum Color {
+    #[default]
+    White,
+    Black,
 }
 
-impl Player {
+impl Color {
     pub fn switch(self) -> Self {
-        if self == Player::Opponent {
-            Player::User
+        if self == Color::Black {
+            Color::White
         } else {
-            Player::Opponent
+            Color::Black
         }
     }
 }
 
-impl fmt::Display for Player {
+impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Player::User => write!(f, "You"),
-            Player::Opponent => write!(f, "Opponent"),
+            Color::White => write!(f, "White"),
+            Color::Black => write!(f, "Black"),
+        }
+    }
+}
+
+/// Empty input picks White.
+impl FromStr for Color {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "w" | "white" => Ok(Color::White),
+            "b" | "black" => Ok(Color::Black),
+            other => Err(format!("Unknown colour: {}", other)),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Outcome {
+    Checkmate {
+        winner: Color,
+    },
+    TimeForfeit {
+        winner: Color,
+    },
+    Resignation {
+        winner: Color,
+    },
+    IllegalMove {
+        winner: Color,
+    },
+    Stalemate,
+    /// The flag fell, but the opponent had no mating material left.
+    TimeoutVsInsufficientMaterial,
+    /// The game was stopped as a draw after too many moves.
+    MoveLimit,
+    DrawAgreed,
+}
+
+impl Outcome {
+    pub fn winner(&self) -> Option<Color> {
+        match *self {
+            Outcome::Checkmate { winner }
+            | Outcome::TimeForfeit { winner }
+            | Outcome::Resignation { winner }
+            | Outcome::IllegalMove { winner } => Some(winner),
+            Outcome::Stalemate
+            | Outcome::TimeoutVsInsufficientMaterial
+            | Outcome::MoveLimit
+            | Outcome::DrawAgreed => None,
         }
     }
+
+    /// The result as written in PGN, e.g. `1-0`.
+    pub fn result(&self) -> &'static str {
+        match self.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+
+    /// The announcement at the end of the game, with each side called by `name`.
+    pub fn describe(&self, name: impl Fn(Color) -> String) -> String {
+        match *self {
+            Outcome::Checkmate { winner } => format!("Checkmate! {} won!", name(winner)),
+            Outcome::TimeForfeit { winner } => format!("Time is up! {} won!", name(winner)),
+            Outcome::Resignation { winner } => {
+                format!("{} resigned. {} won!", name(winner.switch()), name(winner))
+            }
+            Outcome::IllegalMove { winner } => format!(
+                "{} made an illegal move. {} won!",
+                name(winner.switch()),
+                name(winner)
+            ),
+            Outcome::Stalemate => "Stalemate! It's a draw!".to_string(),
+            Outcome::TimeoutVsInsufficientMaterial => {
+                "Time is up, but there is no mating material left. Draw!".to_string()
+            }
+            Outcome::MoveLimit => "The move limit was reached. Draw!".to_string(),
+            Outcome::DrawAgreed => "Draw agreed.".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.describe(|color| color.to_string()))
+    }
+}
+
+#[test]
+fn outcome_names_the_winner() {
+    let outcome = Outcome::Resignation {
+        winner: Color::Black,
+    };
+    assert_eq!(outcome.to_string(), "White resigned. Black won!");
+    let names = |color| match color {
+        Color::White => "Alice".to_string(),
+        Color::Black => "Bob".to_string(),
+    };
+    assert_eq!(outcome.describe(names), "Alice resigned. Bob won!");
 }
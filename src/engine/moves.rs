@@ -0,0 +1,237 @@
+use std::{fmt, str::FromStr};
+
+use super::{game_state::check_if_king_in_check, Color};
+use crate::board::{
+    chesspiece::{get_movements, ChessPiece, ChessPieceType},
+    layout::{Board, BoardCoordinates},
+};
+
+pub const PROMOTIONS: [ChessPieceType; 4] = [
+    ChessPieceType::Queen,
+    ChessPieceType::Knight,
+    ChessPieceType::Rook,
+    ChessPieceType::Bishop,
+];
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub struct Move {
+    pub from: BoardCoordinates,
+    pub to: BoardCoordinates,
+    pub promotion: Option<ChessPieceType>,
+}
+
+impl Move {
+    pub fn is_capture(&self, board: &Board) -> bool {
+        board.at(&self.to).piece.is_some()
+    }
+
+    /// The move as engines exchange it over UCI, e.g. `e2e3` or `a7a8q`.
+    pub fn uci(&self) -> String {
+        let mut uci = format!("{}{}", self.from, self.to);
+        if let Some(promotion) = self.promotion {
+            uci.push_str(&promotion.to_string().to_ascii_lowercase());
+        }
+        uci
+    }
+
+    pub fn from_uci(s: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid UCI move: {}", s);
+        let field = |range| {
+            s.get(range)
+                .and_then(|field: &str| field.parse().ok())
+                .ok_or_else(invalid)
+        };
+        let promotion = match s.get(4..) {
+            Some("") => None,
+            Some(piece) => Some(piece.to_ascii_uppercase().parse::<ChessPieceType>()?),
+            None => return Err(invalid()),
+        };
+        Ok(Move {
+            from: field(0..2)?,
+            to: field(2..4)?,
+            promotion,
+        })
+    }
+}
+
+/// Every move `player` can make without leaving their king in check.
+pub fn legal_moves(board: &Board, player: &Color) -> Vec<Move> {
+    let mut moves = vec![];
+    for field in board.get_all_fields_by_player(player) {
+        let piece = field.piece.unwrap();
+        for to in get_movements(&field, board) {
+            let from = field.coordinates;
+            if piece.can_change(to) {
+                moves.extend(PROMOTIONS.iter().map(|promotion| Move {
+                    from,
+                    to,
+                    promotion: Some(*promotion),
+                }));
+            } else {
+                moves.push(Move {
+                    from,
+                    to,
+                    promotion: None,
+                });
+            }
+        }
+    }
+    moves.retain(|mv| !check_if_king_in_check(&apply_move(board.clone(), mv), player));
+    moves
+}
+
+/// Plays `mv` on `board` without asking about promotions.
+pub fn apply_move(board: Board, mv: &Move) -> Board {
+    let piece = board.at(&mv.from).piece.unwrap();
+    let piece = ChessPiece {
+        piece_type: mv.promotion.unwrap_or(piece.piece_type),
+        ..piece
+    };
+    board.remove_piece(mv.from).add_replace_piece(mv.to, piece)
+}
+
+/// How many games of `depth` plies there are from `board`, for checking the
+/// move generator against known counts.
+pub fn perft(board: &Board, player: Color, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = legal_moves(board, &player);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .iter()
+        .map(|mv| perft(&apply_move(board.clone(), mv), player.switch(), depth - 1))
+        .sum()
+}
+
+/// Standard algebraic notation for `mv` played on `board`, e.g. `Nbd7`,
+/// `exd5`, `e8=Q+` or `Qh4#`.
+pub fn san(board: &Board, mv: &Move) -> String {
+    let piece = board.at(&mv.from).piece.unwrap();
+    let mut san = String::new();
+    if piece.piece_type == ChessPieceType::Pawn {
+        if mv.is_capture(board) {
+            san.push(mv.from.file());
+        }
+    } else {
+        san.push_str(&piece.piece_type.to_string());
+        // Other pieces of the same kind that could also go to the same field.
+        let rivals: Vec<BoardCoordinates> = legal_moves(board, &piece.player)
+            .into_iter()
+            .filter(|other| other.to == mv.to && other.from != mv.from)
+            .filter(|other| {
+                board
+                    .at(&other.from)
+                    .piece
+                    .is_some_and(|rival| rival.piece_type == piece.piece_type)
+            })
+            .map(|other| other.from)
+            .collect();
+        if !rivals.is_empty() {
+            let from = mv.from.to_string();
+            san.push_str(
+                if rivals.iter().all(|rival| rival.file() != mv.from.file()) {
+                    &from[..1]
+                } else if rivals.iter().all(|rival| rival.rank() != mv.from.rank()) {
+                    &from[1..]
+                } else {
+                    &from
+                },
+            );
+        }
+    }
+    if mv.is_capture(board) {
+        san.push('x');
+    }
+    san.push_str(&mv.to.to_string());
+    if let Some(promotion) = mv.promotion {
+        san.push_str(&format!("={}", promotion));
+    }
+
+    let after = apply_move(board.clone(), mv);
+    let opponent = piece.player.switch();
+    if check_if_king_in_check(&after, &opponent) {
+        san.push(if legal_moves(&after, &opponent).is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+/// Reads a move in standard notation like `Nf3` or `bxc8=Q+`, with or
+/// without the check sign and any `!` or `?` after it. Moves written as two
+/// fields, e.g. `g1f3`, are accepted too.
+pub fn from_san(board: &Board, player: Color, text: &str) -> Result<Move, String> {
+    let plain = |san: &str| san.trim_end_matches(['+', '#', '!', '?']).to_string();
+    let wanted = plain(text);
+    legal_moves(board, &player)
+        .into_iter()
+        .find(|mv| plain(&san(board, mv)) == wanted || mv.uci() == wanted.to_ascii_lowercase())
+        .ok_or_else(|| format!("Illegal or unknown move: {}", text))
+}
+
+/// The same as `uci`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.uci())
+    }
+}
+
+/// Parses moves written as two fields and an optional promotion, e.g. `a7a8q`,
+/// in either case.
+impl FromStr for Move {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Move::from_uci(s.trim())
+    }
+}
+
+#[test]
+fn opening_moves() {
+    let board = Board::default().fill_standard_pieces();
+    // Pawns only step one field for now, so 8 pawn moves and 4 knight moves.
+    assert_eq!(legal_moves(&board, &Color::White).len(), 12);
+    assert_eq!(legal_moves(&board, &Color::Black).len(), 12);
+    assert_eq!(perft(&board, Color::White, 1), 12);
+    assert_eq!(perft(&board, Color::White, 2), 144);
+}
+
+#[test]
+fn algebraic_notation() {
+    let board = Board::default().fill_standard_pieces();
+    let notation = |board: &Board, mv: &str| san(board, &mv.parse().unwrap());
+    assert_eq!(notation(&board, "g1f3"), "Nf3");
+    assert_eq!(notation(&board, "e2e3"), "e3");
+
+    let piece = |player, piece_type| ChessPiece { player, piece_type };
+    let at = |field: &str| field.parse::<BoardCoordinates>().unwrap();
+    let board = Board::default()
+        .add_replace_piece(at("e1"), piece(Color::White, ChessPieceType::King))
+        .add_replace_piece(at("a8"), piece(Color::White, ChessPieceType::Rook))
+        .add_replace_piece(at("a6"), piece(Color::White, ChessPieceType::Rook))
+        .add_replace_piece(at("h8"), piece(Color::Black, ChessPieceType::King))
+        .add_replace_piece(at("b7"), piece(Color::White, ChessPieceType::Pawn))
+        .add_replace_piece(at("c8"), piece(Color::Black, ChessPieceType::Knight));
+    assert_eq!(notation(&board, "a6a7"), "R6a7");
+    assert_eq!(notation(&board, "b7c8q"), "bxc8=Q+");
+    assert_eq!(
+        from_san(&board, Color::White, "bxc8=Q+!"),
+        Ok("b7c8q".parse().unwrap())
+    );
+    assert!(from_san(&board, Color::White, "Ra7").is_err());
+}
+
+#[test]
+fn uci_notation() {
+    let mv: Move = "E2E3".parse().unwrap();
+    assert_eq!(mv.uci(), "e2e3");
+    assert_eq!(Move::from_uci("e2e3"), Ok(mv));
+    let promotion = Move::from_uci("a7a8q").unwrap();
+    assert_eq!(promotion.to_string(), "a7a8q");
+    assert!(Move::from_uci("e2e9").is_err());
+    assert!(Move::from_uci("e2").is_err());
+}
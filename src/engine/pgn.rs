@@ -0,0 +1,281 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{
+    evaluation::MATE,
+    moves::{apply_move, from_san, san, Move},
+    Color,
+};
+use crate::board::{
+    fen::{from_fen, fullmove_number},
+    layout::Board,
+};
+
+/// What goes after a move in an annotated game.
+#[derive(Debug, Default)]
+pub struct Annotation {
+    /// A suffix like `?!` or `??`.
+    pub nag: &'static str,
+    pub comment: Option<String>,
+}
+
+/// Today's date in UTC the way the `Date` tag takes it, e.g. `2024.03.09`.
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    date((seconds / 86_400) as i64)
+}
+
+/// The date `days` after 1970-01-01, from the proleptic Gregorian calendar.
+fn date(days: i64) -> String {
+    // Counted in 400-year eras from 0000-03-01, so that leap days end a year.
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// Writes a game as PGN, with the tags in the given order and the moves
+/// wrapped at 80 columns. The game starts from the `FEN` tag if there is one
+/// and from the standard position otherwise.
+pub fn to_pgn(tags: &[(&str, String)], moves: &[Move], result: &str) -> String {
+    annotated_pgn(tags, moves, result, |_| Annotation::default())
+}
+
+/// Like `to_pgn`, with whatever `annotate` has to say about the move at each
+/// ply.
+pub fn annotated_pgn(
+    tags: &[(&str, String)],
+    moves: &[Move],
+    result: &str,
+    annotate: impl Fn(usize) -> Annotation,
+) -> String {
+    let mut pgn: String = tags
+        .iter()
+        .map(|(name, value)| format!("[{} \"{}\"]\n", name, value.replace('"', "'")))
+        .collect();
+    pgn.push('\n');
+
+    let fen = tags.iter().find(|(name, _)| *name == "FEN");
+    let (mut board, player) = fen
+        .and_then(|(_, fen)| from_fen(fen).ok())
+        .unwrap_or((Board::default().fill_standard_pieces(), Color::White));
+    let first_move = fen.map_or(1, |(_, fen)| fullmove_number(fen));
+    // Black may move first in a game set up from a position.
+    let skipped = usize::from(player == Color::Black);
+    let mut tokens = vec![];
+    let mut commented = false;
+    for (index, mv) in moves.iter().enumerate() {
+        let ply = index + skipped;
+        let number = first_move + ply / 2;
+        if ply % 2 == 0 {
+            tokens.push(format!("{}.", number));
+        } else if commented || index == 0 {
+            // Black's move needs its number again after a comment.
+            tokens.push(format!("{}...", number));
+        }
+        let annotation = annotate(index);
+        tokens.push(format!("{}{}", san(&board, mv), annotation.nag));
+        commented = annotation.comment.is_some();
+        if let Some(comment) = annotation.comment {
+            tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+        }
+        board = apply_move(board, mv);
+    }
+    tokens.push(result.to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push_str("\n\n");
+    pgn
+}
+
+/// A game read from PGN.
+#[derive(Debug)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start_board: Board,
+    pub start_player: Color,
+    pub moves: Vec<Move>,
+    /// The comment after each move, if there was one.
+    pub comments: Vec<Option<String>>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads the first game in `text`, starting from the `FEN` tag if there is
+/// one. Variations, NAGs like `$2` and `;` comments are skipped.
+pub fn read_pgn(text: &str) -> Result<PgnGame, String> {
+    let mut game = PgnGame {
+        tags: vec![],
+        start_board: Board::default().fill_standard_pieces(),
+        start_player: Color::White,
+        moves: vec![],
+        comments: vec![],
+        result: "*".to_string(),
+    };
+    let mut board = game.start_board.clone();
+    let mut player = game.start_player;
+    let mut chars = text.chars().peekable();
+    let mut variations: u32 = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let comment: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                match game.comments.last_mut() {
+                    Some(last) if variations == 0 => {
+                        let comment = comment.trim().to_string();
+                        *last = Some(match last.take() {
+                            Some(before) => format!("{} {}", before, comment),
+                            None => comment,
+                        })
+                    }
+                    _ => {}
+                }
+            }
+            ';' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '(' => variations += 1,
+            ')' => {
+                variations = variations.checked_sub(1).ok_or_else(|| {
+                    format!("Move {}: ')' closes no variation", game.moves.len() / 2 + 1)
+                })?
+            }
+            '[' if variations == 0 => {
+                if !game.moves.is_empty() {
+                    break;
+                }
+                let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                let (name, value) = tag
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| format!("Invalid tag: [{}]", tag))?;
+                let value = value.trim().trim_matches('"').to_string();
+                if name == "FEN" {
+                    (board, player) = from_fen(&value)?;
+                    game.start_board = board.clone();
+                    game.start_player = player;
+                }
+                game.tags.push((name.to_string(), value));
+            }
+            c if c.is_whitespace() || variations > 0 => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(next) =
+                    chars.next_if(|c| !c.is_whitespace() && !"{}();[".contains(*c))
+                {
+                    token.push(next);
+                }
+                if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str()) {
+                    game.result = token;
+                    break;
+                }
+                let token = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                if token.is_empty() || token.starts_with('$') {
+                    continue;
+                }
+                let mv = from_san(&board, player, token)
+                    .map_err(|error| format!("Move {}: {}", game.moves.len() / 2 + 1, error))?;
+                board = apply_move(board, &mv);
+                player = player.switch();
+                game.moves.push(mv);
+                game.comments.push(None);
+            }
+        }
+    }
+    Ok(game)
+}
+
+/// The evaluation in a `[%eval 1.25]` or `[%eval #-3]` comment, in
+/// centipawns for White.
+pub fn eval_in(comment: &str) -> Option<i32> {
+    let (_, rest) = comment.split_once("[%eval ")?;
+    let (value, _) = rest.split_once(']')?;
+    let value = value.trim();
+    match value.strip_prefix('#') {
+        Some(mate) if mate.starts_with('-') => Some(-MATE),
+        Some(_) => Some(MATE),
+        None => value
+            .parse::<f64>()
+            .ok()
+            .map(|pawns| (pawns * 100.0).round() as i32),
+    }
+}
+
+#[test]
+fn short_game() {
+    let moves: Vec<Move> = ["e2e3", "f7f6", "d1h5"]
+        .iter()
+        .map(|mv| mv.parse().unwrap())
+        .collect();
+    let pgn = to_pgn(&[("White", "A \"B\"".to_string())], &moves, "*");
+    assert_eq!(pgn, "[White \"A 'B'\"]\n\n1. e3 f6 2. Qh5+ *\n\n");
+
+    let pgn = annotated_pgn(&[], &moves, "*", |ply| Annotation {
+        nag: if ply == 1 { "??" } else { "" },
+        comment: (ply == 0).then(|| "Solid".to_string()),
+    });
+    assert_eq!(pgn, "\n1. e3 {Solid} 1... f6?? 2. Qh5+ *\n\n");
+
+    // Set up with Black to move.
+    let board = apply_move(Board::default().fill_standard_pieces(), &moves[0]);
+    let fen = crate::board::fen::to_fen(&board, Color::Black, 1);
+    let pgn = to_pgn(&[("FEN", fen)], &moves[1..], "*");
+    assert!(pgn.ends_with("\n\n1... f6 2. Qh5+ *\n\n"));
+}
+
+#[test]
+fn reads_games() {
+    let game = read_pgn(
+        "[Event \"Test\"]\n[White \"A\"]\n\n1. e3 {[%eval 0.10]} 1... f6 $2 (1... Nf6 2. Nc3)\n2.Qh5+ {[%eval #-2] Oops} ; the end\n1-0\n\n[Event \"Next\"]",
+    )
+    .unwrap();
+    assert_eq!(game.tag("White"), Some("A"));
+    assert_eq!(game.result, "1-0");
+    assert_eq!(to_pgn(&[], &game.moves, "*"), "\n1. e3 f6 2. Qh5+ *\n\n");
+    let evals: Vec<Option<i32>> = game
+        .comments
+        .iter()
+        .map(|comment| comment.as_deref().and_then(eval_in))
+        .collect();
+    assert_eq!(evals, [Some(10), None, Some(-MATE)]);
+    assert!(read_pgn("1. e3 e3").is_err());
+    assert_eq!(
+        read_pgn("1. e3 (1. d3) ) e6").unwrap_err(),
+        "Move 1: ')' closes no variation"
+    );
+}
+
+#[test]
+fn dates_from_days() {
+    assert_eq!(date(0), "1970.01.01");
+    assert_eq!(date(11_016), "2000.02.29");
+    assert_eq!(date(19_791), "2024.03.09");
+}
@@ -0,0 +1,511 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    clock::TimeControl,
+    controller::{player_label, Chat, PlayerController},
+    game_state::GameState,
+    moves::Move,
+    Color, Outcome,
+};
+use crate::interface::console::outln;
+
+/// Raised whenever the messages change, so that different versions of the
+/// program refuse to play each other instead of misunderstanding moves.
+pub const PROTOCOL_VERSION: u32 = 1;
+const PING_EVERY: Duration = Duration::from_secs(5);
+/// Hearing nothing, not even a ping, for this long means the connection is gone.
+const READ_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long to wait for the other side to come back after a disconnect, as
+/// `--help` tells for `--host` and `--connect`. The game is kept in memory
+/// only, so it is lost if either program is stopped meanwhile.
+const RECONNECT_WITHIN: Duration = Duration::from_secs(120);
+
+/// A line from the other side. Besides the greeting (`hello <version>
+/// <moves seen> <name>`) and, from the host, `setup <colour> <time control>`,
+/// these are all the messages there are.
+#[derive(Debug, PartialEq)]
+enum Message {
+    /// `move <uci> <white ms> <black ms> [draw]`, with the clocks as they
+    /// were after the move.
+    Move {
+        mv: Move,
+        white: Duration,
+        black: Duration,
+        draw: bool,
+    },
+    /// `draw accept` or `draw decline`.
+    DrawAnswer(bool),
+    Resign,
+    Chat(String),
+    Ping,
+    Error(String),
+    Unknown(String),
+    /// Not sent, but reported when the connection is lost.
+    Closed,
+}
+
+fn parse_message(line: &str) -> Message {
+    let line = line.trim();
+    let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+    let words: Vec<&str> = argument.split_whitespace().collect();
+    match (command, &words[..]) {
+        ("move", [mv, white, black, flags @ ..]) => {
+            match (Move::from_uci(mv), white.parse(), black.parse()) {
+                (Ok(mv), Ok(white), Ok(black)) => Message::Move {
+                    mv,
+                    white: Duration::from_millis(white),
+                    black: Duration::from_millis(black),
+                    draw: flags == ["draw"],
+                },
+                _ => Message::Unknown(line.to_string()),
+            }
+        }
+        ("draw", ["accept"]) => Message::DrawAnswer(true),
+        ("draw", ["decline"]) => Message::DrawAnswer(false),
+        ("resign", []) => Message::Resign,
+        ("chat", _) => Message::Chat(argument.to_string()),
+        ("ping", _) => Message::Ping,
+        ("error", _) => Message::Error(argument.to_string()),
+        _ => Message::Unknown(line.to_string()),
+    }
+}
+
+fn send_on(writer: &Mutex<Option<TcpStream>>, line: &str) {
+    if let Some(stream) = writer.lock().unwrap().as_mut() {
+        let _ = writeln!(stream, "{}", line);
+    }
+}
+
+fn read_line_by(reader: &mut BufReader<TcpStream>, deadline: Instant) -> Result<String, String> {
+    let wait = deadline.saturating_duration_since(Instant::now());
+    let _ = reader
+        .get_ref()
+        .set_read_timeout(Some(wait.max(Duration::from_millis(1))));
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Err("The other side hung up".to_string()),
+        Ok(_) => Ok(line),
+        Err(error) => Err(format!("The other side did not answer: {}", error)),
+    }
+}
+
+/// One end of a network game between two instances of this program. The
+/// host keeps its listener so that the other side can reconnect after a
+/// dropped connection.
+pub struct Connection {
+    listener: Option<TcpListener>,
+    address: String,
+    pub name: Option<String>,
+    pub peer_name: Option<String>,
+    writer: Arc<Mutex<Option<TcpStream>>>,
+    messages: Receiver<Message>,
+    /// How many moves the other side had seen when it last said hello.
+    peer_plies: usize,
+}
+
+impl Connection {
+    fn new(listener: Option<TcpListener>, address: String, name: Option<String>) -> Self {
+        let writer = Arc::new(Mutex::new(None));
+        keep_alive(Arc::downgrade(&writer));
+        Connection {
+            listener,
+            address,
+            name,
+            peer_name: None,
+            writer,
+            messages: mpsc::channel().1,
+            peer_plies: 0,
+        }
+    }
+
+    /// Waits on `listener` for the other side, which is told to play
+    /// `their_color` under `time_control`.
+    pub fn host(
+        listener: TcpListener,
+        name: Option<String>,
+        their_color: Color,
+        time_control: &TimeControl,
+    ) -> Result<Self, String> {
+        let (stream, _) = listener
+            .accept()
+            .map_err(|error| format!("Could not accept a connection: {}", error))?;
+        let mut connection = Connection::new(Some(listener), String::new(), name);
+        let reader = connection.greet(stream, 0, Instant::now() + READ_TIMEOUT)?;
+        connection.send(&format!("setup {} {}", their_color, time_control));
+        connection.listen(reader);
+        Ok(connection)
+    }
+
+    /// Joins the game hosted at `address`, returning the colour and time
+    /// control the host picked.
+    pub fn connect(
+        address: &str,
+        name: Option<String>,
+    ) -> Result<(Self, Color, TimeControl), String> {
+        let stream = TcpStream::connect(address)
+            .map_err(|error| format!("Could not connect to {}: {}", address, error))?;
+        let mut connection = Connection::new(None, address.to_string(), name);
+        let deadline = Instant::now() + READ_TIMEOUT;
+        let mut reader = connection.greet(stream, 0, deadline)?;
+        let line = read_line_by(&mut reader, deadline)?;
+        let (color, time_control) = line
+            .trim()
+            .strip_prefix("setup ")
+            .and_then(|setup| setup.split_once(' '))
+            .ok_or_else(|| format!("Expected the game setup, got: {}", line.trim()))?;
+        let (color, time_control) = (color.parse()?, time_control.parse()?);
+        connection.listen(reader);
+        Ok((connection, color, time_control))
+    }
+
+    /// Sends what the player types after `chat` to the other side.
+    pub fn chat(&self) -> Chat {
+        let writer = Arc::clone(&self.writer);
+        Box::new(move |text| send_on(&writer, &format!("chat {}", text)))
+    }
+
+    fn send(&self, line: &str) {
+        send_on(&self.writer, line)
+    }
+
+    /// Exchanges greetings on a fresh connection, having seen `plies` moves.
+    fn greet(
+        &mut self,
+        stream: TcpStream,
+        plies: usize,
+        deadline: Instant,
+    ) -> Result<BufReader<TcpStream>, String> {
+        let mut reader = BufReader::new(
+            stream
+                .try_clone()
+                .map_err(|error| format!("Could not use the connection: {}", error))?,
+        );
+        *self.writer.lock().unwrap() = Some(stream);
+        self.send(&format!(
+            "hello {} {} {}",
+            PROTOCOL_VERSION,
+            plies,
+            self.name.as_deref().unwrap_or("")
+        ));
+        let line = read_line_by(&mut reader, deadline)?;
+        let words: Vec<&str> = line.trim().splitn(4, ' ').collect();
+        let ["hello", version, peer_plies, ref name @ ..] = words[..] else {
+            return Err(format!("Unexpected greeting: {}", line.trim()));
+        };
+        if version != PROTOCOL_VERSION.to_string() {
+            return Err(format!(
+                "The other side speaks version {} of the protocol, this is version {}",
+                version, PROTOCOL_VERSION
+            ));
+        }
+        self.peer_plies = peer_plies
+            .parse()
+            .map_err(|_| format!("Unexpected greeting: {}", line.trim()))?;
+        self.peer_name = name.first().map(|name| name.to_string());
+        Ok(reader)
+    }
+
+    /// Reads messages on a thread of their own and passes them on to
+    /// `receive`.
+    fn listen(&mut self, mut reader: BufReader<TcpStream>) {
+        let (messages, received): (Sender<Message>, _) = mpsc::channel();
+        self.messages = received;
+        thread::spawn(move || {
+            let _ = reader.get_ref().set_read_timeout(Some(READ_TIMEOUT));
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if !matches!(reader.read_line(&mut line), Ok(read) if read > 0) {
+                    break;
+                }
+                match parse_message(&line) {
+                    Message::Ping => {}
+                    message => {
+                        if messages.send(message).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            let _ = messages.send(Message::Closed);
+        });
+    }
+
+    fn peer(&self) -> &str {
+        self.peer_name.as_deref().unwrap_or("Your opponent")
+    }
+
+    /// The next message from the other side other than chat, which is shown
+    /// as it comes, reconnecting if the connection drops. `None` once the
+    /// other side is gone for good.
+    fn receive(&mut self, plies: usize, last_sent: Option<&str>) -> Option<Message> {
+        loop {
+            match self.messages.recv() {
+                Ok(Message::Closed) | Err(_) => {
+                    if let Err(error) = self.reconnect(plies, last_sent) {
+                        outln!("{}", error);
+                        return None;
+                    }
+                }
+                Ok(Message::Chat(text)) => outln!("{}: {}", self.peer(), text),
+                Ok(message) => return Some(message),
+            }
+        }
+    }
+
+    /// Gets the other side back and sends `last_sent` again if it has not
+    /// seen all `plies` moves.
+    fn reconnect(&mut self, plies: usize, last_sent: Option<&str>) -> Result<(), String> {
+        if let Some(stream) = self.writer.lock().unwrap().take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        outln!("The connection was lost. Waiting for {}...", self.peer());
+        let deadline = Instant::now() + RECONNECT_WITHIN;
+        if let Some(listener) = &self.listener {
+            let _ = listener.set_nonblocking(true);
+        }
+        let stream = loop {
+            let attempt = match &self.listener {
+                Some(listener) => listener.accept().map(|(stream, _)| stream),
+                None => TcpStream::connect(&self.address),
+            };
+            match attempt {
+                Ok(stream) => break stream,
+                Err(_) if Instant::now() < deadline => thread::sleep(Duration::from_millis(500)),
+                Err(error) => return Err(format!("{} did not come back: {}", self.peer(), error)),
+            }
+        };
+        let _ = stream.set_nonblocking(false);
+        let reader = self.greet(stream, plies, deadline)?;
+        self.listen(reader);
+        if self.peer_plies < plies {
+            if let Some(line) = last_sent {
+                self.send(line);
+            }
+        }
+        outln!("Reconnected to {}.", self.peer());
+        Ok(())
+    }
+}
+
+/// Pings the other side every few seconds so that it notices when the
+/// connection is gone, until the `Connection` is dropped.
+fn keep_alive(writer: Weak<Mutex<Option<TcpStream>>>) {
+    thread::spawn(move || loop {
+        thread::sleep(PING_EVERY);
+        let Some(writer) = writer.upgrade() else {
+            break;
+        };
+        send_on(&writer, "ping");
+    });
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(stream) = self.writer.lock().unwrap().take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// The side played by whoever is at the other end of a `Connection`. Their
+/// moves are checked like any other, so an illegal one loses the game.
+pub struct RemoteController {
+    connection: Connection,
+    color: Color,
+    /// The last move sent, in case it got lost with the connection.
+    last_sent: Option<String>,
+    last_received: Option<Move>,
+    draw_offered: bool,
+    clock: Option<(Duration, Duration)>,
+}
+
+impl RemoteController {
+    pub fn new(connection: Connection, color: Color) -> Self {
+        RemoteController {
+            connection,
+            color,
+            last_sent: None,
+            last_received: None,
+            draw_offered: false,
+            clock: None,
+        }
+    }
+}
+
+impl PlayerController for RemoteController {
+    fn choose_move(&mut self, game_state: &GameState) -> Option<Move> {
+        outln!(
+            "Waiting for {} to move...",
+            player_label(self.name(), self.color)
+        );
+        let plies = game_state.history.len();
+        loop {
+            match self.connection.receive(plies, self.last_sent.as_deref())? {
+                Message::Move {
+                    mv,
+                    white,
+                    black,
+                    draw,
+                } => {
+                    self.clock = Some((white, black));
+                    self.draw_offered = draw;
+                    self.last_received = Some(mv);
+                    return Some(mv);
+                }
+                Message::Resign => return None,
+                Message::Error(error) => {
+                    outln!("{} reported an error: {}", self.connection.peer(), error);
+                    return None;
+                }
+                Message::Unknown(line) => self
+                    .connection
+                    .send(&format!("error Unknown message: {}", line)),
+                Message::DrawAnswer(_) | Message::Chat(_) | Message::Ping | Message::Closed => {}
+            }
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.connection.peer_name.as_deref()
+    }
+
+    fn opponent_moved(&mut self, game_state: &GameState, mv: Move, draw_offered: bool) {
+        let (white, black) = game_state
+            .clock
+            .as_ref()
+            .map_or((Duration::ZERO, Duration::ZERO), |clock| {
+                (clock.remaining(Color::White), clock.remaining(Color::Black))
+            });
+        let line = format!(
+            "move {} {} {}{}",
+            mv.uci(),
+            white.as_millis(),
+            black.as_millis(),
+            if draw_offered { " draw" } else { "" }
+        );
+        self.connection.send(&line);
+        self.last_sent = Some(line);
+    }
+
+    fn offers_draw(&mut self) -> bool {
+        std::mem::take(&mut self.draw_offered)
+    }
+
+    fn accepts_draw(&mut self, game_state: &GameState) -> bool {
+        outln!(
+            "Waiting for {} to answer the draw offer...",
+            self.connection.peer()
+        );
+        let plies = game_state.history.len();
+        loop {
+            match self.connection.receive(plies, self.last_sent.as_deref()) {
+                Some(Message::DrawAnswer(accepted)) => return accepted,
+                Some(Message::Resign) | None => return false,
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn draw_answered(&mut self, accepted: bool) {
+        self.connection.send(if accepted {
+            "draw accept"
+        } else {
+            "draw decline"
+        });
+    }
+
+    fn game_over(&mut self, game_state: &GameState) {
+        match game_state.outcome {
+            Some(Outcome::Resignation { winner }) if winner == self.color => {
+                self.connection.send("resign")
+            }
+            Some(Outcome::IllegalMove { winner }) if winner != self.color => {
+                if let Some(mv) = self.last_received {
+                    self.connection
+                        .send(&format!("error Illegal move: {}", mv.uci()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn synced_clock(&mut self) -> Option<(Duration, Duration)> {
+        self.clock.take()
+    }
+}
+
+#[test]
+fn plays_over_localhost() {
+    use super::{controller::ScriptedController, game_state::Players};
+    use crate::interface::console;
+
+    let play = |mut players: Players, time_control: TimeControl| {
+        let mut game_state = GameState::start(time_control);
+        loop {
+            game_state = game_state.do_move(&mut players);
+            if game_state.outcome.is_some() {
+                break game_state;
+            }
+            game_state = game_state.switch_player();
+        }
+    };
+    let script = |moves: &str| Box::new(moves.parse::<ScriptedController>().unwrap());
+    let time_control: TimeControl = "5+3".parse().unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let client = thread::spawn(move || {
+        let (connection, color, time_control) =
+            Connection::connect(&address, Some("Bob".to_string())).unwrap();
+        assert_eq!(color, Color::Black);
+        assert_eq!(connection.peer_name.as_deref(), Some("Alice"));
+        let players = Players {
+            white: Box::new(RemoteController::new(connection, Color::White)),
+            black: script("e7e6"),
+        };
+        let mut played = None;
+        let output = console::capture("", || played = Some(play(players, time_control)));
+        assert!(output.contains("Alice: Good luck!"));
+        played.unwrap()
+    });
+    let connection = Connection::host(
+        listener,
+        Some("Alice".to_string()),
+        Color::Black,
+        &time_control,
+    )
+    .unwrap();
+    connection.chat()("Good luck!");
+    let players = Players {
+        white: script("e2e3 d1h5"),
+        black: Box::new(RemoteController::new(connection, Color::Black)),
+    };
+    let hosted = play(players, time_control);
+    let joined = client.join().unwrap();
+
+    assert_eq!(hosted.history, joined.history);
+    assert_eq!(hosted.history.len(), 3);
+    let resigned = Some(Outcome::Resignation {
+        winner: Color::White,
+    });
+    assert_eq!((hosted.outcome, joined.outcome), (resigned, resigned));
+    assert_eq!(
+        parse_message("move e2e4 1000 2500 draw"),
+        Message::Move {
+            mv: Move::from_uci("e2e4").unwrap(),
+            white: Duration::from_millis(1000),
+            black: Duration::from_millis(2500),
+            draw: true,
+        }
+    );
+}
@@ -0,0 +1,224 @@
+use std::{fs, time::Duration};
+
+use super::{
+    clock::{Clock, TimeControl},
+    game_state::{GameState, Players},
+    moves::Move,
+    Color,
+};
+use crate::{
+    board::fen::{from_fen, fullmove_number},
+    common::json::Json,
+};
+
+/// Tells save files apart from other JSON.
+const FORMAT: &str = "chess-save";
+/// Raised whenever the layout of save files changes. Files of any other
+/// version are refused rather than misread.
+pub const VERSION: i64 = 1;
+
+/// A game in progress as it is written to a save file.
+pub struct SavedGame {
+    pub game_state: GameState,
+    /// How each side is set up, the way `--white` and `--black` take it.
+    pub white: String,
+    pub black: String,
+}
+
+impl SavedGame {
+    /// Fails if one of the sides cannot be set up again.
+    pub fn new(game_state: &GameState, players: &Players) -> Result<Self, String> {
+        let setup = |color: Color| {
+            players
+                .get(color)
+                .setup()
+                .ok_or_else(|| format!("Games with {} cannot be saved.", players.label(color)))
+        };
+        Ok(SavedGame {
+            game_state: game_state.clone(),
+            white: setup(Color::White)?,
+            black: setup(Color::Black)?,
+        })
+    }
+
+    pub fn to_json(&self) -> Json {
+        let state = &self.game_state;
+        let clock = state.clock.as_ref().map(|clock| {
+            let millis = |player| clock.remaining(player).as_millis() as i64;
+            Json::object([
+                ("white_ms", millis(Color::White).into()),
+                ("black_ms", millis(Color::Black).into()),
+            ])
+        });
+        Json::object([
+            ("format", FORMAT.into()),
+            ("version", VERSION.into()),
+            ("fen", state.start_fen().into()),
+            (
+                "moves",
+                state
+                    .history
+                    .iter()
+                    .map(Move::uci)
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            (
+                "time_control",
+                state
+                    .clock
+                    .as_ref()
+                    .map_or(TimeControl::Unlimited, Clock::time_control)
+                    .to_string()
+                    .into(),
+            ),
+            ("clock", clock.into()),
+            // As text, since JSON numbers cannot hold every seed.
+            ("seed", state.seed.map(|seed| seed.to_string()).into()),
+            ("white", self.white.as_str().into()),
+            ("black", self.black.as_str().into()),
+        ])
+    }
+
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        if json.get("format").and_then(Json::as_str) != Some(FORMAT) {
+            return Err("This is not a saved game.".to_string());
+        }
+        match json.get("version").and_then(Json::as_f64) {
+            Some(version) if version == VERSION as f64 => {}
+            Some(version) => {
+                return Err(format!(
+                    "The save is in version {} of the format, this program reads version {}.",
+                    version, VERSION
+                ))
+            }
+            None => return Err("The save has no version.".to_string()),
+        }
+        let field = |name| {
+            json.get(name)
+                .and_then(Json::as_str)
+                .ok_or_else(|| format!("The save is corrupted: it has no {}.", name))
+        };
+        let corrupted = |error: String| format!("The save is corrupted: {}", error);
+
+        let (board, player) = from_fen(field("fen")?).map_err(corrupted)?;
+        let time_control: TimeControl = field("time_control")?.parse().map_err(corrupted)?;
+        let mut game_state = GameState::from_position(board, player, time_control.clone());
+        game_state.first_move = fullmove_number(field("fen")?);
+        let moves = json
+            .get("moves")
+            .and_then(Json::as_array)
+            .ok_or_else(|| corrupted("it has no moves.".to_string()))?;
+        for (index, mv) in moves.iter().enumerate() {
+            let played = Move::from_uci(mv.as_str().unwrap_or_default())
+                .and_then(|mv| game_state.clone().play(mv))
+                .map_err(|error| corrupted(format!("move {}: {}", index + 1, error)))?;
+            game_state = played.switch_player();
+        }
+
+        if let Some(clock) = json.get("clock").filter(|clock| **clock != Json::Null) {
+            let millis = |name| {
+                clock
+                    .get(name)
+                    .and_then(Json::as_f64)
+                    .map(|millis| Duration::from_millis(millis as u64))
+                    .ok_or_else(|| corrupted(format!("the clock has no {}.", name)))
+            };
+            // Each side's moves so far, for the stage of the time control.
+            let plies = moves.len() as u32;
+            let first = plies.div_ceil(2);
+            let moves = match game_state.start_player {
+                Color::White => [first, plies / 2],
+                Color::Black => [plies / 2, first],
+            };
+            let remaining = [millis("white_ms")?, millis("black_ms")?];
+            game_state.clock = Clock::resume(time_control, moves, remaining);
+        }
+        game_state.seed = match json.get("seed").and_then(Json::as_str) {
+            Some(seed) => Some(
+                seed.parse()
+                    .map_err(|_| corrupted(format!("invalid seed {}.", seed)))?,
+            ),
+            None => None,
+        };
+        Ok(SavedGame {
+            game_state,
+            white: field("white")?.to_string(),
+            black: field("black")?.to_string(),
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, format!("{}\n", self.to_json()))
+            .map_err(|error| format!("Could not save to {}: {}", path, error))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read {}: {}", path, error))?;
+        text.parse::<Json>()
+            .map_err(|error| format!("The save is corrupted: {}", error))
+            .and_then(|json| SavedGame::from_json(&json))
+            .map_err(|error| format!("Could not load {}: {}", path, error))
+    }
+}
+
+#[test]
+fn saves_and_loads() {
+    use super::{
+        controller::{EngineController, HumanController},
+        search::{Engine, SearchOptions},
+        selfplay::Contestant,
+    };
+
+    let options = SearchOptions {
+        threads: 1,
+        depth: 3,
+        nodes: Some(5000),
+        weights: "100/50".parse().unwrap(),
+        ..Default::default()
+    };
+    let players = Players {
+        white: Box::new(HumanController::new(Some("Alice".to_string()))),
+        black: Box::new(EngineController::new(Engine::new(options))),
+    };
+    // White's second move already reached the second stage, Black has one
+    // move to go.
+    let mut game_state = GameState::start("2/1:2".parse().unwrap());
+    for mv in ["e2e3", "e7e6", "d1g4"] {
+        game_state = game_state
+            .play(Move::from_uci(mv).unwrap())
+            .unwrap()
+            .switch_player();
+    }
+    game_state.seed = Some(u64::MAX);
+
+    let json = SavedGame::new(&game_state, &players).unwrap().to_json();
+    let saved = SavedGame::from_json(&json.to_string().parse().unwrap()).unwrap();
+    assert_eq!(saved.game_state.fen(), game_state.fen());
+    assert_eq!(saved.game_state.history, game_state.history);
+    assert_eq!(saved.game_state.seed, Some(u64::MAX));
+    assert_eq!(saved.white, "human:Alice");
+    let engine: Contestant = saved.black.parse().unwrap();
+    let restored = engine.search_options.unwrap();
+    assert_eq!(
+        (restored.depth, restored.nodes, restored.weights),
+        (3, Some(5000), options.weights)
+    );
+    let clock = saved.game_state.clock.unwrap();
+    assert_eq!(clock.remaining(Color::White), Duration::from_secs(60));
+    assert_eq!(clock.moves_to_go(Color::White), None);
+    assert_eq!(clock.moves_to_go(Color::Black), Some(1));
+
+    let with = |key: &str, value: Json| {
+        let Json::Object(mut fields) = json.clone() else {
+            unreachable!()
+        };
+        fields.retain(|(name, _)| name != key);
+        SavedGame::from_json(&Json::Object(fields).with(key, value))
+    };
+    assert!(with("version", Json::from(2i64)).is_err_and(|error| error.contains("version 2")));
+    assert!(with("format", "other".into()).is_err());
+    assert!(with("moves", vec!["e2e3", "e2e3"].into())
+        .is_err_and(|error| error.contains("corrupted: move 2")));
+}
@@ -0,0 +1,559 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::{
+    evaluation::{evaluate, piece_value, Weights, MATE},
+    game_state::check_if_king_in_check,
+    moves::{apply_move, legal_moves, Move},
+    skill::Skill,
+    transposition::{hash, splitmix64, Bound, Entry, TranspositionTable},
+    Color,
+};
+use crate::board::layout::Board;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchOptions {
+    /// Number of search threads. With 1 thread and no `movetime` the search
+    /// is fully deterministic.
+    pub threads: usize,
+    pub depth: u32,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub hash_megabytes: usize,
+    pub skill: Skill,
+    pub weights: Weights,
+    /// Picks among the moves below full strength, so that the same seed
+    /// plays the same moves.
+    pub seed: u64,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            depth: 5,
+            movetime: Some(Duration::from_secs(5)),
+            nodes: None,
+            hash_megabytes: 16,
+            skill: Skill::default(),
+            weights: Weights::default(),
+            seed: 0,
+        }
+    }
+}
+
+/// Positions from the opening, middlegame and endgame that `bench` times
+/// the search on.
+pub const BENCH_POSITIONS: [&str; 4] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w - - 4 4",
+    "r4rk1/pp2bppp/2n1bn2/q2pp3/3P4/2P1BN2/PP1NBPPP/R2Q1RK1 b - - 0 10",
+    "8/5pk1/6p1/8/3R4/6P1/5PKP/r7 w - - 0 40",
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "depth {}, score {:+.2}, {} nodes",
+            self.depth,
+            self.score as f64 / 100.0,
+            self.nodes
+        )
+    }
+}
+
+/// A searcher whose transposition table survives between moves.
+pub struct Engine {
+    pub options: SearchOptions,
+    table: TranspositionTable,
+    stop: Arc<AtomicBool>,
+}
+
+impl Engine {
+    pub fn new(options: SearchOptions) -> Self {
+        Engine {
+            options,
+            table: TranspositionTable::new(options.hash_megabytes),
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Setting the returned flag makes a running search return as soon as
+    /// possible with the best move of the last finished iteration. A search
+    /// started while it is set returns at once, so whoever starts one clears
+    /// it first.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Lazy SMP: every thread runs its own iterative deepening on the same
+    /// position and they only cooperate through the shared table. Helpers
+    /// start at staggered depths so they fill the table ahead of the main
+    /// thread, whose result is the one returned.
+    pub fn search(&self, board: &Board, player: Color) -> SearchResult {
+        let finished = AtomicBool::new(false);
+        let limits = Limits {
+            depth: self.options.depth,
+            nodes: self.options.nodes,
+            deadline: self
+                .options
+                .movetime
+                .map(|movetime| Instant::now() + movetime),
+            noise: 0,
+        };
+        if !self.options.skill.is_full_strength() {
+            return self.weakened_search(board, player, limits, &finished);
+        }
+
+        thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.options.threads.max(1))
+                .map(|id| {
+                    let mut worker = Worker::new(self, &finished, limits, id);
+                    scope.spawn(move || worker.iterate(board, player).nodes)
+                })
+                .collect();
+
+            let mut result = Worker::new(self, &finished, limits, 0).iterate(board, player);
+            finished.store(true, Ordering::Relaxed);
+            result.nodes += helpers
+                .into_iter()
+                .map(|helper| helper.join().unwrap_or(0))
+                .sum::<u64>();
+            result
+        })
+    }
+
+    /// A single thread scores every root move within the skill's limits,
+    /// then the skill decides which of them to actually play.
+    fn weakened_search(
+        &self,
+        board: &Board,
+        player: Color,
+        limits: Limits,
+        finished: &AtomicBool,
+    ) -> SearchResult {
+        let skill = self.options.skill;
+        let limits = Limits {
+            depth: limits.depth.min(skill.depth()),
+            nodes: Some(
+                limits
+                    .nodes
+                    .map_or(skill.nodes(), |nodes| nodes.min(skill.nodes())),
+            ),
+            noise: skill.noise(),
+            ..limits
+        };
+        let mut worker = Worker::new(self, finished, limits, 0);
+        worker.score_root_moves = true;
+        let mut result = worker.iterate(board, player);
+        let mut rng = StdRng::seed_from_u64(splitmix64(self.options.seed ^ hash(board, &player)));
+        if let Some((chosen, score)) = skill.choose(&worker.root_scores, &mut rng) {
+            result.best_move = Some(chosen);
+            result.score = score;
+        }
+        result
+    }
+}
+
+/// How often a worker looks at the clock and the stop flags.
+const CHECK_INTERVAL: u32 = 1024;
+
+/// Scores at least this far from zero are mates, at most as many plies away
+/// as the table's depth can count.
+const MATE_BOUND: i32 = MATE - u8::MAX as i32;
+
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    depth: u32,
+    nodes: Option<u64>,
+    deadline: Option<Instant>,
+    /// Largest random error added to evaluations, 0 for a clean search.
+    noise: i32,
+}
+
+struct Worker<'a> {
+    engine: &'a Engine,
+    finished: &'a AtomicBool,
+    limits: Limits,
+    id: usize,
+    nodes: u64,
+    stopped: bool,
+    /// Calls to `should_stop` left until it next looks at the clock and flags.
+    until_check: u32,
+    /// Search every root move with a full window so each gets an exact score.
+    score_root_moves: bool,
+    root_scores: Vec<(Move, i32)>,
+}
+
+impl<'a> Worker<'a> {
+    fn new(engine: &'a Engine, finished: &'a AtomicBool, limits: Limits, id: usize) -> Self {
+        Worker {
+            engine,
+            finished,
+            limits,
+            id,
+            nodes: 0,
+            stopped: false,
+            // Look at once, in case the search was stopped before it began.
+            until_check: 1,
+            score_root_moves: false,
+            root_scores: vec![],
+        }
+    }
+
+    fn iterate(&mut self, board: &Board, player: Color) -> SearchResult {
+        let mut result = SearchResult {
+            best_move: legal_moves(board, &player).first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+        };
+        let first_depth = 1 + self.id as u32 % 2;
+        for depth in first_depth..=self.limits.depth.max(first_depth) {
+            let (score, best_move) = self.root(board, player, depth);
+            if self.stopped {
+                break;
+            }
+            result = SearchResult {
+                best_move: best_move.or(result.best_move),
+                score,
+                depth,
+                nodes: 0,
+            };
+            if score.abs() >= MATE - depth as i32 {
+                break;
+            }
+        }
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn root(&mut self, board: &Board, player: Color, depth: u32) -> (i32, Option<Move>) {
+        let key = hash(board, &player);
+        let moves = self.ordered(board, legal_moves(board, &player), key);
+        let (mut alpha, beta) = (-MATE, MATE);
+        let mut best_move = None;
+        let mut root_scores = vec![];
+        for mv in moves {
+            let child = apply_move(board.clone(), &mv);
+            let window = if self.score_root_moves { -MATE } else { alpha };
+            let score = -self.negamax(&child, player.switch(), depth - 1, 1, -beta, -window);
+            if self.stopped {
+                break;
+            }
+            root_scores.push((mv, score));
+            if score > alpha || best_move.is_none() {
+                alpha = score;
+                best_move = Some(mv);
+            }
+        }
+        if !self.stopped {
+            self.store(key, depth, alpha, Bound::Exact, best_move);
+            self.root_scores = root_scores;
+        }
+        (alpha, best_move)
+    }
+
+    fn negamax(
+        &mut self,
+        board: &Board,
+        player: Color,
+        depth: u32,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        if depth == 0 {
+            return self.quiescence(board, player, alpha, beta);
+        }
+        self.nodes += 1;
+
+        let key = hash(board, &player);
+        if let Some(entry) = self.engine.table.probe(key) {
+            if entry.depth as u32 >= depth {
+                let score = from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let moves = legal_moves(board, &player);
+        if moves.is_empty() {
+            return if check_if_king_in_check(board, &player) {
+                -MATE + ply
+            } else {
+                0
+            };
+        }
+
+        let original_alpha = alpha;
+        let mut best_move = None;
+        for mv in self.ordered(board, moves, key) {
+            let child = apply_move(board.clone(), &mv);
+            let score = -self.negamax(&child, player.switch(), depth - 1, ply + 1, -beta, -alpha);
+            if self.stopped {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        let bound = if alpha >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.store(key, depth, to_table(alpha, ply), bound, best_move);
+        alpha
+    }
+
+    fn quiescence(&mut self, board: &Board, player: Color, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        let stand_pat =
+            evaluate(board, &player, &self.engine.options.weights) + self.noise(board, &player);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut captures: Vec<Move> = legal_moves(board, &player)
+            .into_iter()
+            .filter(|mv| mv.is_capture(board))
+            .collect();
+        captures.sort_by_key(|mv| -capture_gain(board, mv));
+        for mv in captures {
+            if self.should_stop() {
+                return 0;
+            }
+            let child = apply_move(board.clone(), &mv);
+            let score = -self.quiescence(&child, player.switch(), -beta, -alpha);
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Table move first, then captures by most valuable victim. Helpers
+    /// rotate the quiet moves so that threads explore different subtrees.
+    fn ordered(&self, board: &Board, moves: Vec<Move>, key: u64) -> Vec<Move> {
+        // A table move can come from a colliding position, so it is only
+        // trusted when it is one of the generated moves.
+        let table_move = self
+            .engine
+            .table
+            .probe(key)
+            .and_then(|entry| entry.best_move)
+            .filter(|mv| moves.contains(mv));
+        let (mut captures, mut quiet): (Vec<Move>, Vec<Move>) =
+            moves.into_iter().partition(|mv| mv.is_capture(board));
+        captures.sort_by_key(|mv| -capture_gain(board, mv));
+        if !quiet.is_empty() {
+            let shift = self.id % quiet.len();
+            quiet.rotate_left(shift);
+        }
+
+        let mut ordered: Vec<Move> = table_move.into_iter().collect();
+        ordered.extend(
+            captures
+                .into_iter()
+                .chain(quiet)
+                .filter(|mv| Some(*mv) != table_move),
+        );
+        ordered
+    }
+
+    fn store(&self, key: u64, depth: u32, score: i32, bound: Bound, best_move: Option<Move>) {
+        self.engine.table.store(
+            key,
+            Entry {
+                depth: depth.min(u8::MAX as u32) as u8,
+                score,
+                bound,
+                best_move,
+            },
+        );
+    }
+
+    /// The same position always gets the same error, so the table stays consistent.
+    fn noise(&self, board: &Board, player: &Color) -> i32 {
+        if self.limits.noise == 0 {
+            return 0;
+        }
+        let spread = 2 * self.limits.noise as u64 + 1;
+        (splitmix64(hash(board, player)) % spread) as i32 - self.limits.noise
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes) {
+            self.stopped = true;
+        }
+        self.until_check -= 1;
+        if !self.stopped && self.until_check == 0 {
+            self.until_check = CHECK_INTERVAL;
+            self.stopped = self.engine.stop.load(Ordering::Relaxed)
+                || (self.id != 0 && self.finished.load(Ordering::Relaxed))
+                || self
+                    .limits
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.stopped
+    }
+}
+
+/// Mate scores count plies from the root, but the table is shared between
+/// roots, so it keeps them counted from the position they belong to.
+fn to_table(score: i32, ply: i32) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply
+    } else if score <= -MATE_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn from_table(score: i32, ply: i32) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply
+    } else if score <= -MATE_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
+fn capture_gain(board: &Board, mv: &Move) -> i32 {
+    let victim = board
+        .at(&mv.to)
+        .piece
+        .map_or(0, |piece| piece_value(piece.piece_type));
+    let attacker = board
+        .at(&mv.from)
+        .piece
+        .map_or(0, |piece| piece_value(piece.piece_type));
+    victim * 10 - attacker / 10
+}
+
+#[test]
+fn single_thread_search_is_deterministic() {
+    use crate::board::{
+        chesspiece::ChessPiece, chesspiece::ChessPieceType, layout::BoardCoordinates,
+    };
+
+    let piece = |player, piece_type| ChessPiece { player, piece_type };
+    let at = |index| BoardCoordinates::from_index(index).unwrap();
+    // Black's queen on D5 hangs to White's knight on E7.
+    let board = Board::default()
+        .add_replace_piece(at(60), piece(Color::White, ChessPieceType::King))
+        .add_replace_piece(at(52), piece(Color::White, ChessPieceType::Knight))
+        .add_replace_piece(at(4), piece(Color::Black, ChessPieceType::King))
+        .add_replace_piece(at(35), piece(Color::Black, ChessPieceType::Queen));
+
+    let options = SearchOptions {
+        threads: 1,
+        depth: 3,
+        movetime: None,
+        nodes: None,
+        hash_megabytes: 1,
+        skill: Skill::default(),
+        weights: Weights::default(),
+        seed: 0,
+    };
+    let first = Engine::new(options).search(&board, Color::White);
+    let second = Engine::new(options).search(&board, Color::White);
+    assert_eq!(first.best_move, second.best_move);
+    assert_eq!(first.nodes, second.nodes);
+    assert_eq!(first.best_move.map(|mv| mv.to), Some(at(35)));
+
+    // A weak engine picks the same of its candidate moves with the same seed.
+    let weak = SearchOptions {
+        skill: "1".parse().unwrap(),
+        seed: 5,
+        ..options
+    };
+    let first = Engine::new(weak).search(&board, Color::White);
+    let second = Engine::new(weak).search(&board, Color::White);
+    assert_eq!(first.best_move, second.best_move);
+}
+
+#[test]
+fn stop_signal_halts_all_threads() {
+    let engine = Engine::new(SearchOptions {
+        threads: 4,
+        depth: 64,
+        movetime: None,
+        nodes: None,
+        hash_megabytes: 1,
+        skill: Skill::default(),
+        weights: Weights::default(),
+        seed: 0,
+    });
+    let stop = engine.stop_handle();
+    let board = Board::default().fill_standard_pieces();
+    let started = Instant::now();
+    let result = thread::scope(|scope| {
+        let search = scope.spawn(|| engine.search(&board, Color::White));
+        thread::sleep(Duration::from_millis(200));
+        stop.store(true, Ordering::Relaxed);
+        search.join().unwrap()
+    });
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(result.best_move.is_some());
+}
+
+#[test]
+fn a_stop_before_the_search_is_kept() {
+    let engine = Engine::new(SearchOptions {
+        depth: 64,
+        movetime: None,
+        ..SearchOptions::default()
+    });
+    engine.stop_handle().store(true, Ordering::Relaxed);
+    let result = engine.search(&Board::default().fill_standard_pieces(), Color::White);
+    assert_eq!(result.depth, 0);
+    assert!(result.best_move.is_some());
+}
+
+#[test]
+fn mate_scores_are_stored_from_their_position() {
+    let score = MATE - 5;
+    assert_eq!(to_table(score, 3), MATE - 2);
+    assert_eq!(from_table(to_table(score, 3), 7), MATE - 9);
+    assert_eq!(from_table(to_table(-score, 3), 1), -(MATE - 3));
+    assert_eq!(to_table(150, 3), 150);
+}
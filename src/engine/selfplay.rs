@@ -0,0 +1,363 @@
+use std::{
+    fmt,
+    io::Write,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::{
+    clock::TimeControl,
+    controller::{EngineController, PlayerController, RandomController},
+    game_state::{GameState, Players},
+    moves::Move,
+    pgn::{to_pgn, today},
+    search::{Engine, SearchOptions},
+    transposition::splitmix64,
+    Color, Outcome,
+};
+
+/// Short openings, one per line, so that deterministic engines do not play
+/// the same game over and over. Pawns only step one field for now.
+pub const OPENINGS: &str = "\
+e2e3 e7e6
+d2d3 d7d6
+g1f3 g8f6
+c2c3 e7e6
+e2e3 c7c6
+b1c3 d7d6
+g2g3 g8f6
+b2b3 e7e6
+";
+
+/// One opening per line as moves like `e2e3 e7e6`; empty lines and lines
+/// starting with `#` are skipped.
+pub fn parse_openings(text: &str) -> Result<Vec<Vec<Move>>, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split_whitespace().map(Move::from_str).collect())
+        .collect()
+}
+
+/// One side of a match: the random mover or an engine with its settings.
+#[derive(Debug, Clone)]
+pub struct Contestant {
+    pub name: String,
+    pub search_options: Option<SearchOptions>,
+}
+
+impl Contestant {
+    /// The random mover plays with `seed`; engines keep the seed in their
+    /// settings.
+    pub fn controller(&self, seed: u64) -> Box<dyn PlayerController> {
+        match self.search_options {
+            Some(options) => Box::new(EngineController::new(Engine::new(options))),
+            None => Box::new(RandomController::seeded(seed)),
+        }
+    }
+}
+
+/// `random`, or `engine` with optional settings after a colon, e.g.
+/// `engine:depth=3,weights=100/50,skill=12,nodes=20000,movetime=500,threads=2,seed=7`.
+/// Engines search on one thread to a fixed depth unless told otherwise.
+impl FromStr for Contestant {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_string();
+        let (kind, settings) = name.split_once(':').unwrap_or((&name, ""));
+        let search_options = match kind {
+            "random" if settings.is_empty() => None,
+            "engine" => {
+                let mut options = SearchOptions {
+                    threads: 1,
+                    movetime: None,
+                    ..SearchOptions::default()
+                };
+                for setting in settings.split(',').filter(|setting| !setting.is_empty()) {
+                    let (key, value) = setting
+                        .split_once('=')
+                        .ok_or_else(|| format!("Settings must look like depth=3: {}", setting))?;
+                    let invalid = |_| format!("Invalid {}: {}", key, value);
+                    match key {
+                        "depth" => options.depth = value.parse().map_err(invalid)?,
+                        "threads" => options.threads = value.parse().map_err(invalid)?,
+                        "nodes" => options.nodes = Some(value.parse().map_err(invalid)?),
+                        "movetime" => {
+                            options.movetime = Some(std::time::Duration::from_millis(
+                                value.parse().map_err(invalid)?,
+                            ))
+                        }
+                        "skill" => options.skill = value.parse()?,
+                        "weights" => options.weights = value.parse()?,
+                        "seed" => options.seed = value.parse().map_err(invalid)?,
+                        other => return Err(format!("Unknown engine setting: {}", other)),
+                    }
+                }
+                Some(options)
+            }
+            _ => return Err(format!("Unknown contestant: {}", name)),
+        };
+        Ok(Contestant {
+            name,
+            search_options,
+        })
+    }
+}
+
+/// The engine with all its settings, written the way `Contestant` reads it.
+pub fn engine_setup(options: &SearchOptions) -> String {
+    let mut setup = format!(
+        "engine:depth={},threads={},skill={},weights={},seed={}",
+        options.depth,
+        options.threads,
+        options.skill.level(),
+        options.weights,
+        options.seed
+    );
+    if let Some(nodes) = options.nodes {
+        setup.push_str(&format!(",nodes={}", nodes));
+    }
+    if let Some(movetime) = options.movetime {
+        setup.push_str(&format!(",movetime={}", movetime.as_millis()));
+    }
+    setup
+}
+
+/// Results from the point of view of the first contestant.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Score {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Score {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn add(&mut self, outcome: &Outcome, first: Color) {
+        match outcome.winner() {
+            Some(winner) if winner == first => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    /// Points per game, from 0 to 1, and an even 0.5 before any game.
+    pub fn ratio(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// The Elo difference the score suggests and the margin of its 95%
+    /// confidence interval, which has no bound before any game.
+    pub fn elo(&self) -> (f64, f64) {
+        if self.games() == 0 {
+            return (0.0, f64::INFINITY);
+        }
+        let games = self.games() as f64;
+        let ratio = self.ratio();
+        let variance = (self.wins as f64 * (1.0 - ratio).powi(2)
+            + self.draws as f64 * (0.5 - ratio).powi(2)
+            + self.losses as f64 * ratio.powi(2))
+            / games;
+        let deviation = (variance / games).sqrt();
+        let (low, high) = (
+            (ratio - 1.96 * deviation).max(0.0),
+            (ratio + 1.96 * deviation).min(1.0),
+        );
+        let margin = (elo_from_ratio(high) - elo_from_ratio(low)) / 2.0;
+        // Without a single loss (or win) the interval has no finite bound.
+        let margin = if margin.is_nan() {
+            f64::INFINITY
+        } else {
+            margin
+        };
+        (elo_from_ratio(ratio), margin)
+    }
+}
+
+pub fn elo_from_ratio(ratio: f64) -> f64 {
+    -400.0 * (1.0 / ratio - 1.0).log10()
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (elo, margin) = self.elo();
+        write!(
+            f,
+            "W/D/L {}/{}/{} [{:.3}], Elo difference {:+.1} +/- {:.1}",
+            self.wins,
+            self.draws,
+            self.losses,
+            self.ratio(),
+            elo,
+            margin
+        )
+    }
+}
+
+pub struct MatchOptions {
+    pub games: u32,
+    pub openings: Vec<Vec<Move>>,
+    /// Games still running after this many moves by both sides are drawn.
+    pub max_plies: usize,
+    /// Each game's random moves follow from this and the game's number.
+    pub seed: u64,
+}
+
+/// Each opening is played twice in a row, once with each colour.
+pub fn opening_for(openings: &[Vec<Move>], game: u32) -> &[Move] {
+    match openings.len() {
+        0 => &[],
+        count => &openings[(game as usize / 2) % count],
+    }
+}
+
+/// Plays one game without drawing anything, starting after `opening`. Once
+/// `stop` is set it gives up after the current move, with no outcome.
+pub fn play_game(
+    players: &mut Players,
+    opening: &[Move],
+    max_plies: usize,
+    stop: &AtomicBool,
+) -> Result<GameState, String> {
+    let mut game_state = GameState::start(TimeControl::Unlimited);
+    for mv in opening {
+        game_state = game_state.play(*mv)?.switch_player();
+    }
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            return Ok(game_state);
+        }
+        game_state = game_state.do_move(players);
+        if game_state.outcome.is_some() {
+            return Ok(game_state);
+        }
+        if game_state.history.len() >= max_plies {
+            game_state.outcome = Some(Outcome::MoveLimit);
+            return Ok(game_state);
+        }
+        game_state = game_state.switch_player();
+    }
+}
+
+/// Plays `options.games` games between the two contestants, swapping colours
+/// after every game and moving on to the next opening after every pair.
+/// Prints the running score and appends each game to `pgn`.
+pub fn run_match(
+    first: &Contestant,
+    second: &Contestant,
+    options: &MatchOptions,
+    mut pgn: Option<&mut dyn Write>,
+) -> Result<Score, String> {
+    let mut score = Score::default();
+    for game in 0..options.games {
+        let opening = opening_for(&options.openings, game);
+        let (white, black, first_color) = if game % 2 == 0 {
+            (first, second, Color::White)
+        } else {
+            (second, first, Color::Black)
+        };
+        let seed = splitmix64(options.seed.wrapping_add(game as u64));
+        let mut players = Players {
+            white: white.controller(seed),
+            black: black.controller(splitmix64(seed)),
+        };
+        let game_state = play_game(
+            &mut players,
+            opening,
+            options.max_plies,
+            &AtomicBool::new(false),
+        )?;
+        let outcome = game_state.outcome.unwrap();
+        score.add(&outcome, first_color);
+        println!(
+            "Game {} of {}: {} vs {}: {} ({})",
+            game + 1,
+            options.games,
+            white.name,
+            black.name,
+            outcome.result(),
+            outcome
+        );
+        println!("{} vs {}: {}", first.name, second.name, score);
+
+        if let Some(pgn) = pgn.as_mut() {
+            let tags = [
+                ("Event", "Self-play match".to_string()),
+                ("Site", "?".to_string()),
+                ("Date", today()),
+                ("Round", (game + 1).to_string()),
+                ("White", white.name.clone()),
+                ("Black", black.name.clone()),
+                ("Result", outcome.result().to_string()),
+                ("Termination", outcome.to_string()),
+                ("Seed", seed.to_string()),
+            ];
+            pgn.write_all(to_pgn(&tags, &game_state.history, outcome.result()).as_bytes())
+                .map_err(|error| format!("Could not write the PGN: {}", error))?;
+        }
+    }
+    Ok(score)
+}
+
+#[test]
+fn score_and_elo() {
+    let even = Score {
+        wins: 3,
+        draws: 4,
+        losses: 3,
+    };
+    assert_eq!(even.elo().0, 0.0);
+    assert!(even.elo().1 > 0.0);
+    let mut score = Score::default();
+    score.add(
+        &Outcome::Checkmate {
+            winner: Color::Black,
+        },
+        Color::Black,
+    );
+    score.add(&Outcome::Stalemate, Color::White);
+    score.add(
+        &Outcome::Checkmate {
+            winner: Color::Black,
+        },
+        Color::White,
+    );
+    assert_eq!(
+        score,
+        Score {
+            wins: 1,
+            draws: 1,
+            losses: 1
+        }
+    );
+    assert!((elo_from_ratio(0.75) - 190.8).abs() < 0.1);
+    assert_eq!(Score::default().ratio(), 0.5);
+    assert_eq!(Score::default().elo(), (0.0, f64::INFINITY));
+}
+
+#[test]
+fn random_match_writes_every_game() {
+    let random: Contestant = "random".parse().unwrap();
+    let engine: Contestant = "engine:depth=1,weights=100/0".parse().unwrap();
+    assert!("engine:depth".parse::<Contestant>().is_err());
+    let options = MatchOptions {
+        games: 2,
+        openings: parse_openings(OPENINGS).unwrap(),
+        max_plies: 12,
+        seed: 1,
+    };
+    let mut pgn = vec![];
+    let score = run_match(&engine, &random, &options, Some(&mut pgn)).unwrap();
+    assert_eq!(score.games(), 2);
+    let pgn = String::from_utf8(pgn).unwrap();
+    assert_eq!(pgn.matches("[Event ").count(), 2);
+    assert_eq!(pgn.matches("[Site \"?\"]").count(), 2);
+    assert_eq!(pgn.matches(&format!("[Date \"{}\"]", today())).count(), 2);
+    assert!(pgn.contains("1. e3 e6"));
+}
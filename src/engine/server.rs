@@ -0,0 +1,958 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use super::{
+    clock::{Clock, TimeControl},
+    game_state::GameState,
+    moves::{legal_moves, san, Move},
+    search::{Engine, SearchOptions},
+    Color, Outcome,
+};
+use crate::{
+    board::{
+        fen::{from_fen, to_fen, STARTING_FEN},
+        layout::Board,
+    },
+    common::{
+        json::Json,
+        websocket::{self, Frame, FrameReader},
+    },
+};
+
+/// Request bodies larger than this are refused.
+const MAX_BODY: usize = 1 << 20;
+
+/// What a connection following a game is woken up for.
+enum Feed {
+    Event(Json),
+    Received(String),
+    Ping(Vec<u8>),
+    Closed,
+    /// The client broke the protocol.
+    Invalid,
+}
+
+/// A game held by the server. The clock of the side to move runs between
+/// requests.
+struct Game {
+    state: GameState,
+    time_control: TimeControl,
+    /// Played at the terminal, so it is only watched through the server.
+    local: bool,
+    /// Everything that happened, numbered from 1, for those who reconnect.
+    events: Vec<Json>,
+    watchers: Vec<Sender<Feed>>,
+}
+
+impl Game {
+    fn new(board: Board, player: Color, time_control: TimeControl) -> Self {
+        let mut state = GameState::from_position(board, player, time_control.clone());
+        if let Some(clock) = state.clock.as_mut() {
+            clock.start(player);
+        }
+        let mut game = Game {
+            state,
+            time_control,
+            local: false,
+            events: vec![],
+            watchers: vec![],
+        };
+        game.finish_if_over();
+        game
+    }
+
+    /// Logs an event and passes it on to everyone watching.
+    fn publish(&mut self, kind: &str, details: Json) {
+        let mut event = Json::object([
+            ("seq", (self.events.len() as i64 + 1).into()),
+            ("type", kind.into()),
+        ]);
+        if let Json::Object(fields) = details {
+            for (key, value) in fields {
+                event = event.with(&key, value);
+            }
+        }
+        self.watchers
+            .retain(|watcher| watcher.send(Feed::Event(event.clone())).is_ok());
+        self.events.push(event);
+    }
+
+    fn publish_position(&mut self, kind: &str, mv: Option<(Move, String)>) {
+        let mut details = Json::object([
+            ("fen", self.state.fen().into()),
+            ("clock", self.state.clock.as_ref().map(clock_json).into()),
+        ]);
+        if let Some((mv, san)) = mv {
+            details = details
+                .with("move", mv.uci().into())
+                .with("san", san.into());
+        }
+        self.publish(kind, details);
+    }
+
+    fn end(&mut self, outcome: Outcome) {
+        self.state.outcome = Some(outcome);
+        if let Some(clock) = self.state.clock.as_mut() {
+            clock.stop();
+        }
+        self.publish(
+            "game_over",
+            Json::object([
+                ("result", outcome.result().into()),
+                ("outcome", outcome.to_string().into()),
+            ]),
+        );
+    }
+
+    /// Ends the game if the side to move has no moves or no time left. The
+    /// terminal decides that for its own games.
+    fn finish_if_over(&mut self) {
+        if self.state.outcome.is_some() || self.local {
+            return;
+        }
+        let player = self.state.current_player;
+        let winner = player.switch();
+        let flagged = self
+            .state
+            .clock
+            .as_ref()
+            .is_some_and(|clock| clock.remaining(player).is_zero());
+        let outcome = if flagged {
+            Some(if self.state.board.has_mating_material(&winner) {
+                Outcome::TimeForfeit { winner }
+            } else {
+                Outcome::TimeoutVsInsufficientMaterial
+            })
+        } else {
+            self.state.outcome_without_moves()
+        };
+        if let Some(outcome) = outcome {
+            self.end(outcome);
+        }
+    }
+
+    fn playable(&mut self) -> Result<(), (u16, String)> {
+        self.finish_if_over();
+        if self.local {
+            Err((409, "This game is played at the terminal".to_string()))
+        } else if self.state.outcome.is_some() {
+            Err((409, "The game is over".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn play(&mut self, mv: Move) -> Result<(), (u16, String)> {
+        self.playable()?;
+        let player = self.state.current_player;
+        let next = self.state.clone().play(mv).map_err(|error| (400, error))?;
+        let san = san(&self.state.board, &mv);
+        self.state = next.switch_player();
+        if let Some(clock) = self.state.clock.as_mut() {
+            clock.stop();
+            clock.start(player.switch());
+        }
+        self.publish_position("move", Some((mv, san)));
+        self.finish_if_over();
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<(), (u16, String)> {
+        if self.local {
+            return Err((409, "This game is played at the terminal".to_string()));
+        }
+        if self.state.history.is_empty() {
+            return Err((400, "There is no move to take back".to_string()));
+        }
+        self.state = self.state.clone().undo();
+        let player = self.state.current_player;
+        if let Some(clock) = self.state.clock.as_mut() {
+            clock.stop();
+            clock.start(player);
+        }
+        self.publish_position("undo", None);
+        Ok(())
+    }
+
+    /// Catches up with the same game played at the terminal.
+    fn follow(&mut self, state: &GameState) {
+        self.state.clock = state.clock.clone();
+        if let Some(clock) = self.state.clock.as_mut() {
+            if state.outcome.is_none() && clock.running().is_none() {
+                clock.start(self.state.current_player);
+            }
+        }
+        let kept = self
+            .state
+            .history
+            .iter()
+            .zip(&state.history)
+            .take_while(|(ours, theirs)| ours == theirs)
+            .count();
+        while self.state.history.len() > kept {
+            self.state = self.state.clone().undo();
+            self.publish_position("undo", None);
+        }
+        for &mv in &state.history[kept..] {
+            let Ok(next) = self.state.clone().play(mv) else {
+                break;
+            };
+            let san = san(&self.state.board, &mv);
+            self.state = next.switch_player();
+            self.publish_position("move", Some((mv, san)));
+        }
+        if let (None, Some(outcome)) = (self.state.outcome, state.outcome) {
+            self.end(outcome);
+        }
+    }
+
+    fn to_json(&self, id: u32) -> Json {
+        let state = &self.state;
+        let moves = |moves: &[Move]| moves.iter().map(Move::uci).collect::<Vec<_>>();
+        let legal = match state.outcome {
+            Some(_) => vec![],
+            None => legal_moves(&state.board, &state.current_player),
+        };
+        Json::object([
+            ("id", i64::from(id).into()),
+            ("fen", state.fen().into()),
+            (
+                "turn",
+                state.current_player.to_string().to_lowercase().into(),
+            ),
+            ("moves", moves(&state.history).into()),
+            ("legal_moves", moves(&legal).into()),
+            (
+                "result",
+                state.outcome.as_ref().map_or("*", Outcome::result).into(),
+            ),
+            (
+                "outcome",
+                state.outcome.map(|outcome| outcome.to_string()).into(),
+            ),
+            ("time_control", self.time_control.to_string().into()),
+            ("clock", state.clock.as_ref().map(clock_json).into()),
+        ])
+    }
+
+    /// What is saved to disk: enough to play the game again from the start.
+    /// The clocks start over from the time control.
+    fn record(&self, id: u32) -> Json {
+        Json::object([
+            ("id", i64::from(id).into()),
+            (
+                "fen",
+                to_fen(&self.state.start_board, self.state.start_player, 1).into(),
+            ),
+            ("time_control", self.time_control.to_string().into()),
+            (
+                "moves",
+                self.state
+                    .history
+                    .iter()
+                    .map(Move::uci)
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+        ])
+    }
+
+    fn from_record(record: &Json) -> Result<(u32, Game), String> {
+        let field = |name| {
+            record
+                .get(name)
+                .and_then(Json::as_str)
+                .ok_or_else(|| format!("The saved game has no {}", name))
+        };
+        let id = record
+            .get("id")
+            .and_then(Json::as_f64)
+            .ok_or("The saved game has no id")? as u32;
+        let (board, player) = from_fen(field("fen")?)?;
+        let mut game = Game::new(board, player, field("time_control")?.parse()?);
+        for mv in record.get("moves").and_then(Json::as_array).unwrap_or(&[]) {
+            let mv = Move::from_uci(mv.as_str().unwrap_or_default())?;
+            game.play(mv).map_err(|(_, error)| error)?;
+        }
+        Ok((id, game))
+    }
+}
+
+fn clock_json(clock: &Clock) -> Json {
+    let millis = |player| clock.remaining(player).as_millis() as i64;
+    Json::object([
+        ("white_ms", millis(Color::White).into()),
+        ("black_ms", millis(Color::Black).into()),
+        (
+            "running",
+            clock
+                .running()
+                .map(|player| player.to_string().to_lowercase())
+                .into(),
+        ),
+    ])
+}
+
+/// Games in memory, optionally saved to a directory with one file per game.
+pub struct Server {
+    games: Mutex<BTreeMap<u32, Game>>,
+    store: Option<PathBuf>,
+}
+
+impl Server {
+    /// Loads any games saved in `store` earlier.
+    pub fn new(store: Option<PathBuf>) -> Result<Self, String> {
+        let mut games = BTreeMap::new();
+        if let Some(store) = &store {
+            fs::create_dir_all(store)
+                .map_err(|error| format!("Could not create {}: {}", store.display(), error))?;
+            let entries = fs::read_dir(store)
+                .map_err(|error| format!("Could not read {}: {}", store.display(), error))?;
+            for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                {
+                    let (id, game) = fs::read_to_string(&path)
+                        .map_err(|error| error.to_string())
+                        .and_then(|text| Game::from_record(&text.parse()?))
+                        .map_err(|error| format!("Could not load {}: {}", path.display(), error))?;
+                    games.insert(id, game);
+                }
+            }
+        }
+        Ok(Server {
+            games: Mutex::new(games),
+            store,
+        })
+    }
+
+    fn save(&self, id: u32, game: &Game) {
+        if let Some(store) = &self.store {
+            let path = store.join(format!("{}.json", id));
+            if let Err(error) = fs::write(&path, game.record(id).to_string()) {
+                eprintln!("Could not save {}: {}", path.display(), error);
+            }
+        }
+    }
+
+    /// Answers one request with a status code and a JSON body:
+    ///
+    /// * `GET /games` lists the games
+    /// * `POST /games` starts one, optionally with a `fen` and a `time_control`
+    /// * `GET /games/<id>` shows one
+    /// * `POST /games/<id>/moves` plays the `move` in the body, e.g. `e2e4`
+    /// * `POST /games/<id>/undo` takes back the last move
+    /// * `POST /games/<id>/engine-move` lets the engine move, searching to
+    ///   `depth` or for `movetime` milliseconds
+    ///
+    /// `/games/<id>/events` is a WebSocket instead, see `stream_events`.
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> (u16, Json) {
+        let body = match body.trim() {
+            "" => Json::Object(vec![]),
+            body => match body.parse::<Json>() {
+                Ok(body) => body,
+                Err(error) => return error_reply(400, format!("Invalid JSON: {}", error)),
+            },
+        };
+        let segments: Vec<&str> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let id = match segments.get(1).map(|id| id.parse::<u32>()) {
+            Some(Ok(id)) => Some(id),
+            Some(Err(_)) => return error_reply(404, format!("No such game: {}", segments[1])),
+            None => None,
+        };
+        let result = match (method, &segments[..], id) {
+            ("GET", ["games"], _) => Ok((200, self.list())),
+            ("POST", ["games"], _) => self.create(&body),
+            ("GET", ["games", _], Some(id)) => self.with_game(id, |_| Ok(())),
+            ("POST", ["games", _, "moves"], Some(id)) => {
+                let mv = body
+                    .get("move")
+                    .and_then(Json::as_str)
+                    .ok_or((400, "The body needs a move".to_string()))
+                    .and_then(|mv| Move::from_uci(mv).map_err(|error| (400, error)));
+                mv.and_then(|mv| self.with_game(id, |game| game.play(mv)))
+            }
+            ("POST", ["games", _, "undo"], Some(id)) => self.with_game(id, Game::undo),
+            ("POST", ["games", _, "engine-move"], Some(id)) => self.engine_move(id, &body),
+            ("GET", ["games", _, "events"], Some(_)) => Err((
+                400,
+                "Follow the events of a game with a WebSocket".to_string(),
+            )),
+            (_, ["games", ..], _) => Err((405, format!("{} is not allowed here", method))),
+            _ => Err((404, format!("Not found: {}", path))),
+        };
+        result.unwrap_or_else(|(status, error)| error_reply(status, error))
+    }
+
+    fn list(&self) -> Json {
+        let mut games = self.games.lock().unwrap();
+        Json::Array(
+            games
+                .iter_mut()
+                .map(|(&id, game)| {
+                    game.finish_if_over();
+                    game.to_json(id)
+                })
+                .collect(),
+        )
+    }
+
+    fn create(&self, body: &Json) -> Result<(u16, Json), (u16, String)> {
+        let text = |name| body.get(name).and_then(Json::as_str);
+        let (board, player) =
+            from_fen(text("fen").unwrap_or(STARTING_FEN)).map_err(|error| (400, error))?;
+        let time_control = text("time_control")
+            .unwrap_or("")
+            .parse::<TimeControl>()
+            .map_err(|error| (400, error))?;
+        let game = Game::new(board, player, time_control);
+        let (_, reply) = self.insert(game);
+        Ok((201, reply))
+    }
+
+    fn insert(&self, game: Game) -> (u32, Json) {
+        let mut games = self.games.lock().unwrap();
+        let id = games.keys().next_back().map_or(1, |last| last + 1);
+        if !game.local {
+            self.save(id, &game);
+        }
+        let reply = game.to_json(id);
+        games.insert(id, game);
+        (id, reply)
+    }
+
+    /// Adds a game played at the terminal, so that it can be followed. It
+    /// cannot be changed through the server.
+    pub fn watch(&self, state: &GameState, time_control: TimeControl) -> u32 {
+        let mut game = Game::new(state.start_board.clone(), state.start_player, time_control);
+        game.local = true;
+        game.follow(state);
+        self.insert(game).0
+    }
+
+    /// Brings a game added with `watch` up to date.
+    pub fn update(&self, id: u32, state: &GameState) {
+        if let Some(game) = self.games.lock().unwrap().get_mut(&id) {
+            game.follow(state);
+        }
+    }
+
+    /// Registers `watcher` for the events of a game and returns what to send
+    /// it first: the events after `since`, or else the whole game.
+    fn subscribe(
+        &self,
+        id: u32,
+        since: Option<usize>,
+        watcher: Sender<Feed>,
+    ) -> Result<Vec<Json>, String> {
+        let mut games = self.games.lock().unwrap();
+        let game = games
+            .get_mut(&id)
+            .ok_or_else(|| format!("No such game: {}", id))?;
+        game.finish_if_over();
+        game.watchers.push(watcher);
+        Ok(match since {
+            Some(since) => game.events.iter().skip(since).cloned().collect(),
+            None => vec![Json::object([
+                ("type", "state".into()),
+                ("seq", (game.events.len() as i64).into()),
+                ("game", game.to_json(id)),
+            ])],
+        })
+    }
+
+    /// A move sent by whoever follows the game as `player`.
+    fn move_from(&self, id: u32, player: Option<Color>, message: &str) -> Result<(), String> {
+        let player = player.ok_or("Spectators cannot move")?;
+        let mv = message
+            .parse::<Json>()?
+            .get("move")
+            .and_then(Json::as_str)
+            .ok_or_else(|| "Send a move like {\"move\":\"e2e4\"}".to_string())
+            .and_then(Move::from_uci)?;
+        self.with_game(id, |game| {
+            if game.state.current_player != player {
+                return Err((409, "It is not your move".to_string()));
+            }
+            game.play(mv)
+        })
+        .map(|_| ())
+        .map_err(|(_, error)| error)
+    }
+
+    /// The clocks of a game, while they are running.
+    fn running_clock(&self, id: u32) -> Option<Json> {
+        let mut games = self.games.lock().unwrap();
+        let game = games.get_mut(&id)?;
+        game.finish_if_over();
+        let clock = game.state.clock.as_ref()?;
+        clock.running().map(|_| clock_json(clock))
+    }
+
+    /// Runs `change` on a game, saves it if anything changed and replies
+    /// with the game as it is afterwards.
+    fn with_game(
+        &self,
+        id: u32,
+        change: impl FnOnce(&mut Game) -> Result<(), (u16, String)>,
+    ) -> Result<(u16, Json), (u16, String)> {
+        let mut games = self.games.lock().unwrap();
+        let game = games
+            .get_mut(&id)
+            .ok_or_else(|| (404, format!("No such game: {}", id)))?;
+        let plies = game.state.history.len();
+        game.finish_if_over();
+        change(game)?;
+        if game.state.history.len() != plies {
+            self.save(id, game);
+        }
+        Ok((200, game.to_json(id)))
+    }
+
+    /// Searches without holding on to the games, so that other requests are
+    /// answered meanwhile. The move is dropped if the game moved on.
+    fn engine_move(&self, id: u32, body: &Json) -> Result<(u16, Json), (u16, String)> {
+        let (board, player, plies) = {
+            let mut games = self.games.lock().unwrap();
+            let game = games
+                .get_mut(&id)
+                .ok_or_else(|| (404, format!("No such game: {}", id)))?;
+            game.playable()?;
+            let state = &game.state;
+            (
+                state.board.clone(),
+                state.current_player,
+                state.history.len(),
+            )
+        };
+        let number = |name| body.get(name).and_then(Json::as_f64);
+        let base = SearchOptions::default();
+        let options = SearchOptions {
+            depth: number("depth").map_or(base.depth, |depth| depth.max(1.0) as u32),
+            movetime: number("movetime")
+                .map(|millis| Duration::from_millis(millis as u64))
+                .or(base.movetime),
+            ..base
+        };
+        let result = Engine::new(options).search(&board, player);
+        let mv = result
+            .best_move
+            .ok_or((409, "The engine has no move".to_string()))?;
+        let (status, game) = self.with_game(id, |game| {
+            if game.state.history.len() != plies {
+                return Err((409, "The game changed during the search".to_string()));
+            }
+            game.play(mv)
+        })?;
+        let search = Json::object([
+            ("move", mv.uci().into()),
+            ("score", i64::from(result.score).into()),
+            ("depth", i64::from(result.depth).into()),
+            ("nodes", (result.nodes as i64).into()),
+        ]);
+        Ok((status, game.with("search", search)))
+    }
+}
+
+fn error_reply(status: u16, error: String) -> (u16, Json) {
+    (status, Json::object([("error", error.into())]))
+}
+
+/// Serves the JSON API over HTTP/1.1, one thread per connection. Replies
+/// allow any origin, so that a page served from elsewhere can use it.
+pub fn serve(listener: TcpListener, server: Arc<Server>) {
+    for stream in listener.incoming().flatten() {
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(30)));
+            if let Err(error) = answer(&stream, &server) {
+                let (status, body) = error_reply(400, error);
+                let _ = respond(&stream, status, Some(&body));
+            }
+        });
+    }
+}
+
+fn answer(stream: &TcpStream, server: &Server) -> Result<(), String> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|error| error.to_string())?;
+    let mut words = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (words.next(), words.next()) else {
+        return Err(format!("Invalid request: {}", request_line.trim()));
+    };
+    let mut headers = vec![];
+    loop {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(|error| error.to_string())?;
+        match header.trim().split_once(':') {
+            Some((name, value)) => {
+                headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            }
+            None => break,
+        }
+    }
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (key, upgrade) = (header("sec-websocket-key"), header("upgrade"));
+    if key.is_some() || upgrade.is_some() {
+        let websocket = upgrade.is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+        return match key {
+            Some(key) if method == "GET" && websocket => {
+                stream_events(stream, server, path, query, key)
+            }
+            _ => Err("A WebSocket handshake needs GET, Upgrade: websocket and a key".to_string()),
+        };
+    }
+    let length = header("content-length").map_or(Ok(0), |length| {
+        length
+            .parse()
+            .map_err(|_| format!("Invalid Content-Length: {}", length))
+    })?;
+    if length > MAX_BODY {
+        return Err("The request body is too large".to_string());
+    }
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|error| error.to_string())?;
+    let body = String::from_utf8(body).map_err(|_| "The body is not UTF-8".to_string())?;
+
+    if method == "OPTIONS" {
+        return respond(stream, 204, None).map_err(|error| error.to_string());
+    }
+    let (status, reply) = server.handle(method, path, &body);
+    respond(stream, status, Some(&reply)).map_err(|error| error.to_string())
+}
+
+/// Streams the events of a game over a WebSocket: `move`, `undo` and
+/// `game_over`, each numbered by `seq`. It starts with the whole game as
+/// `state`, or with the events after `since=<seq>` for someone coming back
+/// after losing the connection. The clocks are sent every second while they
+/// run. Connecting with `player=white` or `player=black` allows sending moves
+/// like `{"move":"e2e4"}`; everyone else only watches.
+fn stream_events(
+    stream: &TcpStream,
+    server: &Server,
+    path: &str,
+    query: &str,
+    key: &str,
+) -> Result<(), String> {
+    let parameter = |name: &str| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+    };
+    let since = parameter("since")
+        .map(|since| {
+            since
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid since: {}", since))
+        })
+        .transpose()?;
+    let player = parameter("player").map(str::parse::<Color>).transpose()?;
+    let (feed, fed) = mpsc::channel();
+    let id = match path.split('/').collect::<Vec<_>>()[..] {
+        ["", "games", id, "events"] => id.parse::<u32>().ok(),
+        _ => None,
+    };
+    let first = id
+        .ok_or_else(|| format!("Not found: {}", path))
+        .and_then(|id| server.subscribe(id, since, feed.clone()));
+    let (Some(id), Ok(first)) = (id, first.as_ref()) else {
+        let error = first.err().unwrap_or_default();
+        let (status, body) = error_reply(404, error);
+        return respond(stream, status, Some(&body)).map_err(|error| error.to_string());
+    };
+
+    let mut writer = stream;
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        websocket::accept_key(key)
+    )
+    .map_err(|error| error.to_string())?;
+    let send = |mut writer: &TcpStream, message: &Json| {
+        websocket::write_frame(
+            &mut writer,
+            websocket::TEXT,
+            message.to_string().as_bytes(),
+            None,
+        )
+    };
+    for event in first {
+        send(writer, event).map_err(|error| error.to_string())?;
+    }
+
+    let reader = stream.try_clone().map_err(|error| error.to_string())?;
+    let _ = reader.set_read_timeout(None);
+    let mut reader = FrameReader::new(reader, true);
+    thread::spawn(move || loop {
+        let message = match reader.read_frame() {
+            Ok(Frame::Text(text)) => Feed::Received(text),
+            Ok(Frame::Ping(payload)) => Feed::Ping(payload),
+            Ok(Frame::Pong) => continue,
+            Err(error) if error.kind() == ErrorKind::InvalidData => Feed::Invalid,
+            Ok(Frame::Close(_)) | Err(_) => Feed::Closed,
+        };
+        let closed = matches!(message, Feed::Closed | Feed::Invalid);
+        if feed.send(message).is_err() || closed {
+            break;
+        }
+    });
+
+    let mut status = None;
+    loop {
+        let sent = match fed.recv_timeout(Duration::from_secs(1)) {
+            Ok(Feed::Event(event)) => send(writer, &event),
+            Ok(Feed::Received(message)) => match server.move_from(id, player, &message) {
+                Ok(()) => Ok(()),
+                Err(error) => send(
+                    writer,
+                    &Json::object([("type", "error".into()), ("error", error.into())]),
+                ),
+            },
+            Ok(Feed::Ping(payload)) => {
+                websocket::write_frame(&mut writer, websocket::PONG, &payload, None)
+            }
+            Ok(Feed::Invalid) => {
+                status = Some(websocket::PROTOCOL_ERROR.to_be_bytes());
+                break;
+            }
+            Ok(Feed::Closed) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => match server.running_clock(id) {
+                Some(clock) => send(
+                    writer,
+                    &Json::object([("type", "clock".into()), ("clock", clock)]),
+                ),
+                None => Ok(()),
+            },
+        };
+        if sent.is_err() {
+            break;
+        }
+    }
+    let status = status.as_ref().map_or(&[][..], |status| &status[..]);
+    let _ = websocket::write_frame(&mut writer, websocket::CLOSE, status, None);
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(())
+}
+
+fn respond(mut stream: &TcpStream, status: u16, body: Option<&Json>) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    };
+    let body = body.map(Json::to_string).unwrap_or_default();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[test]
+fn serves_games_over_http() {
+    let store = std::env::temp_dir().join(format!("chess-server-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&store);
+    let server = Server::new(Some(store.clone())).unwrap();
+
+    let (status, game) = server.handle("POST", "/games", "");
+    assert_eq!(status, 201);
+    assert_eq!(game.get("fen").and_then(Json::as_str), Some(STARTING_FEN));
+    assert_eq!(
+        game.get("legal_moves")
+            .and_then(Json::as_array)
+            .map(<[_]>::len),
+        Some(12)
+    );
+    let (status, game) = server.handle("POST", "/games/1/moves", r#"{"move":"e2e3"}"#);
+    assert_eq!(status, 200);
+    assert_eq!(game.get("turn").and_then(Json::as_str), Some("black"));
+    assert_eq!(
+        server
+            .handle("POST", "/games/1/moves", r#"{"move":"e2e4"}"#)
+            .0,
+        400
+    );
+    assert_eq!(server.handle("POST", "/games/1/undo", "").0, 200);
+    let (status, game) = server.handle("POST", "/games/1/engine-move", r#"{"depth":1}"#);
+    assert_eq!(status, 200);
+    assert!(game
+        .get("search")
+        .and_then(|search| search.get("move"))
+        .is_some());
+
+    let (status, game) = server.handle(
+        "POST",
+        "/games",
+        r#"{"fen":"4k3/8/8/8/8/8/8/4K3 w - - 0 1","time_control":"5+3"}"#,
+    );
+    assert_eq!(status, 201);
+    assert!(game
+        .get("clock")
+        .and_then(|clock| clock.get("white_ms"))
+        .is_some());
+    assert_eq!(server.handle("GET", "/games/3", "").0, 404);
+    assert_eq!(server.handle("DELETE", "/games/1", "").0, 405);
+
+    // The games come back from the store, and the API answers plain HTTP.
+    let server = Arc::new(Server::new(Some(store.clone())).unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || serve(listener, server));
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET /games HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert!(reply.starts_with("HTTP/1.1 200 OK\r\n"));
+    let games: Json = reply.split("\r\n\r\n").nth(1).unwrap().parse().unwrap();
+    let games = games.as_array().unwrap();
+    assert_eq!(games.len(), 2);
+    assert_eq!(
+        games[0]
+            .get("moves")
+            .and_then(Json::as_array)
+            .map(<[_]>::len),
+        Some(1)
+    );
+    let _ = fs::remove_dir_all(&store);
+}
+
+#[test]
+fn streams_events_to_watchers() {
+    let server = Arc::new(Server::new(None).unwrap());
+    let mut local = GameState::start(TimeControl::Unlimited);
+    let id = server.watch(&local, TimeControl::Unlimited);
+    server.handle("POST", "/games", "");
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let serving = Arc::clone(&server);
+    thread::spawn(move || serve(listener, serving));
+
+    let connect = |game: u32, query: &str| {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET /games/{}/events{} HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            game, query
+        )
+        .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with("HTTP/1.1 101"));
+        while line.trim() != "" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+        (stream, FrameReader::new(reader, false))
+    };
+    let next = |reader: &mut FrameReader<BufReader<TcpStream>>| match reader.read_frame().unwrap() {
+        Frame::Text(text) => text.parse::<Json>().unwrap(),
+        other => panic!("Unexpected frame: {:?}", other),
+    };
+    let kind = |event: &Json| {
+        event
+            .get("type")
+            .and_then(Json::as_str)
+            .unwrap()
+            .to_string()
+    };
+
+    // A game played at the terminal is followed move by move.
+    let (_watching, mut watcher) = connect(id, "");
+    assert_eq!(kind(&next(&mut watcher)), "state");
+    local = local.play(Move::from_uci("e2e3").unwrap()).unwrap();
+    server.update(id, &local);
+    let event = next(&mut watcher);
+    assert_eq!(kind(&event), "move");
+    assert_eq!(event.get("san").and_then(Json::as_str), Some("e3"));
+    assert_eq!(server.handle("POST", "/games/1/undo", "").0, 409);
+
+    // Coming back with `since` replays what was missed.
+    let (_again, mut replayed) = connect(id, "?since=0");
+    assert_eq!(next(&mut replayed).get("seq"), Some(&Json::Number(1.0)));
+
+    // Players of a game on the server can move through the stream,
+    // spectators cannot.
+    let (mut playing, mut player) = connect(2, "?player=white");
+    assert_eq!(kind(&next(&mut player)), "state");
+    websocket::write_frame(
+        &mut playing,
+        websocket::TEXT,
+        br#"{"move":"d2d3"}"#,
+        Some([7; 4]),
+    )
+    .unwrap();
+    assert_eq!(
+        next(&mut player).get("move").and_then(Json::as_str),
+        Some("d2d3")
+    );
+    websocket::write_frame(
+        &mut playing,
+        websocket::TEXT,
+        br#"{"move":"d3d4"}"#,
+        Some([7; 4]),
+    )
+    .unwrap();
+    assert_eq!(kind(&next(&mut player)), "error");
+
+    // A client must mask its frames.
+    websocket::write_frame(&mut playing, websocket::TEXT, b"{}", None).unwrap();
+    assert_eq!(
+        player.read_frame().unwrap(),
+        Frame::Close(Some(websocket::PROTOCOL_ERROR))
+    );
+
+    // Only a GET can be upgraded.
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "POST /games/{}/events HTTP/1.1\r\nUpgrade: websocket\r\n\
+         Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        id
+    )
+    .unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert!(reply.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+}
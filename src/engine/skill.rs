@@ -0,0 +1,114 @@
+use std::{fmt, str::FromStr};
+
+use rand::Rng;
+
+use super::moves::Move;
+
+pub const MAX_LEVEL: u8 = 20;
+
+/// Playing strength from 1 (beginner) to 20 (full strength). Lower levels
+/// search shallower and fewer nodes, misjudge positions by a random margin
+/// and now and then play a plausible move that is not the best one.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Skill {
+    level: u8,
+}
+
+impl Skill {
+    pub fn from_level(level: u8) -> Option<Self> {
+        (1..=MAX_LEVEL).contains(&level).then_some(Skill { level })
+    }
+
+    /// Very rough mapping: level 1 plays around 600 Elo, level 20 around 2400.
+    pub fn from_elo(elo: u32) -> Self {
+        let level = 1 + elo.saturating_sub(600).min(1800) * 19 / 1800;
+        Skill { level: level as u8 }
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn is_full_strength(&self) -> bool {
+        self.level == MAX_LEVEL
+    }
+
+    pub fn depth(&self) -> u32 {
+        1 + self.level as u32 / 4
+    }
+
+    pub fn nodes(&self) -> u64 {
+        50 * (self.level as u64).pow(2)
+    }
+
+    /// Largest error, in centipawns, added to every evaluation.
+    pub fn noise(&self) -> i32 {
+        (MAX_LEVEL - self.level) as i32 * 10
+    }
+
+    /// Picks the move to play from every root move and its score: usually
+    /// the best one, otherwise any move not much worse than it.
+    pub fn choose(&self, scored_moves: &[(Move, i32)], rng: &mut impl Rng) -> Option<(Move, i32)> {
+        let best = scored_moves
+            .iter()
+            .copied()
+            .max_by_key(|(_, score)| *score)?;
+        let margin = (MAX_LEVEL - self.level) as i32 * 15;
+        let plausible: Vec<(Move, i32)> = scored_moves
+            .iter()
+            .copied()
+            .filter(|(_, score)| *score >= best.1 - margin)
+            .collect();
+        let best_chance = 0.3 + 0.035 * self.level as f64;
+        if rng.gen_bool(best_chance.min(1.0)) {
+            Some(best)
+        } else {
+            Some(plausible[rng.gen_range(0..plausible.len())])
+        }
+    }
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Skill { level: MAX_LEVEL }
+    }
+}
+
+impl fmt::Display for Skill {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "level {}", self.level)
+    }
+}
+
+/// Accepts a level like `7` or an Elo limit like `1500elo`. Empty means full strength.
+impl FromStr for Skill {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        if s.is_empty() {
+            return Ok(Skill::default());
+        }
+        if let Some(elo) = s.strip_suffix("elo") {
+            return elo
+                .trim()
+                .parse::<u32>()
+                .map(Skill::from_elo)
+                .map_err(|_| format!("Invalid Elo: {}", elo));
+        }
+        s.parse::<u8>()
+            .ok()
+            .and_then(Skill::from_level)
+            .ok_or_else(|| format!("Level must be between 1 and {}: {}", MAX_LEVEL, s))
+    }
+}
+
+#[test]
+fn skill_parsing() {
+    assert_eq!(Skill::from_str("\n"), Ok(Skill::default()));
+    assert_eq!(Skill::from_str("7"), Ok(Skill { level: 7 }));
+    assert_eq!(Skill::from_str("600 Elo"), Ok(Skill { level: 1 }));
+    assert_eq!(Skill::from_str("1500elo"), Ok(Skill { level: 10 }));
+    assert_eq!(Skill::from_str("9000elo"), Ok(Skill::default()));
+    assert!(Skill::from_str("21").is_err());
+    assert!(Skill::from_str("0").is_err());
+}
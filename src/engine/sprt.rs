@@ -0,0 +1,260 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use super::{
+    game_state::Players,
+    moves::Move,
+    selfplay::{opening_for, play_game, Score},
+    uci::{UciController, UciEngine},
+    Color, Outcome,
+};
+
+pub struct SprtOptions {
+    /// Elo difference of the null hypothesis, that the first engine is no
+    /// better than this.
+    pub elo0: f64,
+    /// Elo difference of the alternative hypothesis.
+    pub elo1: f64,
+    /// Chance of accepting H1 although H0 is true.
+    pub alpha: f64,
+    /// Chance of accepting H0 although H1 is true.
+    pub beta: f64,
+    /// Stop without a verdict after this many games.
+    pub max_games: u32,
+    /// Number of games played at the same time.
+    pub concurrency: usize,
+    /// Search limits sent with every `go`, e.g. `depth 4` or `movetime 100`.
+    pub go: String,
+    pub openings: Vec<Vec<Move>>,
+    pub max_plies: usize,
+}
+
+impl SprtOptions {
+    /// The LLR at which the test stops, accepting H0 below and H1 above.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+}
+
+/// Log-likelihood ratio of H1 against H0 for `score`, using the normal
+/// approximation of the game results.
+pub fn llr(score: &Score, elo0: f64, elo1: f64) -> f64 {
+    let games = score.games() as f64;
+    if score.games() == 0 {
+        return 0.0;
+    }
+    let ratio = score.ratio();
+    let variance = (score.wins as f64 * (1.0 - ratio).powi(2)
+        + score.draws as f64 * (0.5 - ratio).powi(2)
+        + score.losses as f64 * ratio.powi(2))
+        / games;
+    if variance == 0.0 {
+        return 0.0;
+    }
+    let expected = |elo: f64| 1.0 / (1.0 + 10f64.powf(-elo / 400.0));
+    let (s0, s1) = (expected(elo0), expected(elo1));
+    games * (s1 - s0) * (2.0 * ratio - s0 - s1) / (2.0 * variance)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Verdict {
+    AcceptH0,
+    AcceptH1,
+    Inconclusive,
+    /// Stopped by an error before a verdict.
+    Aborted,
+}
+
+pub struct SprtSummary {
+    pub score: Score,
+    pub llr: f64,
+    pub bounds: (f64, f64),
+    pub verdict: Verdict,
+}
+
+impl fmt::Display for SprtSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.verdict {
+            Verdict::AcceptH0 => writeln!(f, "H0 accepted: the change is not an improvement.")?,
+            Verdict::AcceptH1 => writeln!(f, "H1 accepted: the change is an improvement.")?,
+            Verdict::Inconclusive => writeln!(f, "No verdict within the game limit.")?,
+            Verdict::Aborted => writeln!(f, "Stopped by an error before a verdict.")?,
+        }
+        writeln!(f, "Games: {}, {}", self.score.games(), self.score)?;
+        write!(
+            f,
+            "LLR: {:.2} ({:.2}, {:.2})",
+            self.llr, self.bounds.0, self.bounds.1
+        )
+    }
+}
+
+/// Plays games between the UCI engines started by the commands `first` and
+/// `second` until the LLR leaves its bounds, printing the running score.
+/// Games in progress when a verdict is reached are abandoned. An engine that
+/// crashes, stops answering or fails to start loses the game; one that does
+/// not start at all is an error before the first game.
+pub fn run_sprt(first: &str, second: &str, options: &SprtOptions) -> Result<SprtSummary, String> {
+    for command in [first, second] {
+        UciEngine::spawn(command)?;
+    }
+    let bounds = options.bounds();
+    let next_game = AtomicU32::new(0);
+    let done = AtomicBool::new(false);
+    let (results, finished_games) = mpsc::channel::<Result<Option<(Outcome, Color)>, String>>();
+
+    thread::scope(|scope| {
+        for _ in 0..options.concurrency.max(1) {
+            let results = results.clone();
+            let (next_game, done) = (&next_game, &done);
+            scope.spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    let game = next_game.fetch_add(1, Ordering::Relaxed);
+                    if game >= options.max_games {
+                        break;
+                    }
+                    let result = play_sprt_game(first, second, options, game, done);
+                    if results.send(result).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(results);
+
+        let mut summary = SprtSummary {
+            score: Score::default(),
+            llr: 0.0,
+            bounds,
+            verdict: Verdict::Inconclusive,
+        };
+        for result in finished_games {
+            let (outcome, first_color) = match result {
+                Ok(Some(result)) => result,
+                Ok(None) => continue,
+                Err(error) => {
+                    done.store(true, Ordering::Relaxed);
+                    summary.verdict = Verdict::Aborted;
+                    println!("{}", summary);
+                    return Err(error);
+                }
+            };
+            summary.score.add(&outcome, first_color);
+            summary.llr = llr(&summary.score, options.elo0, options.elo1);
+            println!(
+                "Game {}: {} ({}), LLR {:.2} ({:.2}, {:.2})",
+                summary.score.games(),
+                outcome.result(),
+                summary.score,
+                summary.llr,
+                bounds.0,
+                bounds.1
+            );
+            if summary.llr <= bounds.0 {
+                summary.verdict = Verdict::AcceptH0;
+            } else if summary.llr >= bounds.1 {
+                summary.verdict = Verdict::AcceptH1;
+            }
+            if summary.verdict != Verdict::Inconclusive {
+                done.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+        Ok(summary)
+    })
+}
+
+/// Returns the outcome and the colour the first engine played, or `None` if
+/// the game was abandoned because `done` was set.
+fn play_sprt_game(
+    first: &str,
+    second: &str,
+    options: &SprtOptions,
+    game: u32,
+    done: &AtomicBool,
+) -> Result<Option<(Outcome, Color)>, String> {
+    let (white, black, first_color) = if game.is_multiple_of(2) {
+        (first, second, Color::White)
+    } else {
+        (second, first, Color::Black)
+    };
+    let spawn = |command, color: Color| {
+        UciEngine::spawn(command)
+            .map(|engine| Box::new(UciController::new(engine, &options.go)))
+            .map_err(|error| {
+                println!("{}, so {} loses game {}.", error, color, game + 1);
+                Outcome::Resignation {
+                    winner: color.switch(),
+                }
+            })
+    };
+    let mut players = match (spawn(white, Color::White), spawn(black, Color::Black)) {
+        (Ok(white), Ok(black)) => Players { white, black },
+        (Err(forfeit), _) | (_, Err(forfeit)) => return Ok(Some((forfeit, first_color))),
+    };
+    let opening = opening_for(&options.openings, game);
+    let game_state = play_game(&mut players, opening, options.max_plies, done)?;
+    Ok(game_state.outcome.map(|outcome| (outcome, first_color)))
+}
+
+#[test]
+fn likelihood_ratio() {
+    let options = SprtOptions {
+        elo0: 0.0,
+        elo1: 5.0,
+        alpha: 0.05,
+        beta: 0.05,
+        max_games: 0,
+        concurrency: 1,
+        go: String::new(),
+        openings: vec![],
+        max_plies: 0,
+    };
+    let (lower, upper) = options.bounds();
+    assert!((lower + 2.944).abs() < 0.001 && (upper - 2.944).abs() < 0.001);
+
+    let even = Score {
+        wins: 100,
+        draws: 100,
+        losses: 100,
+    };
+    assert!(llr(&even, 0.0, 5.0) < 0.0);
+    let winning = Score {
+        wins: 400,
+        draws: 100,
+        losses: 200,
+    };
+    assert!(llr(&winning, 0.0, 5.0) > upper);
+    assert_eq!(llr(&Score::default(), 0.0, 5.0), 0.0);
+}
+
+#[test]
+fn an_engine_that_fails_to_start_loses() {
+    let options = SprtOptions {
+        elo0: 0.0,
+        elo1: 5.0,
+        alpha: 0.05,
+        beta: 0.05,
+        max_games: 2,
+        concurrency: 1,
+        go: String::new(),
+        openings: vec![],
+        max_plies: 0,
+    };
+    let missing = "/nonexistent/engine";
+    let result = play_sprt_game(missing, missing, &options, 1, &AtomicBool::new(false));
+    let white_lost = Outcome::Resignation {
+        winner: Color::Black,
+    };
+    assert_eq!(result, Ok(Some((white_lost, Color::Black))));
+    assert!(run_sprt(missing, missing, &options).is_err());
+}
@@ -0,0 +1,182 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::{
+    moves::{Move, PROMOTIONS},
+    Color,
+};
+use crate::board::{
+    chesspiece::{ChessPiece, ChessPieceType},
+    layout::{Board, BoardCoordinates},
+};
+
+pub const fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const fn zobrist_keys() -> [[u64; 64]; 12] {
+    let mut keys = [[0; 64]; 12];
+    let mut state = 0x5EED_u64;
+    let mut piece = 0;
+    while piece < 12 {
+        let mut field = 0;
+        while field < 64 {
+            state = splitmix64(state);
+            keys[piece][field] = state;
+            field += 1;
+        }
+        piece += 1;
+    }
+    keys
+}
+
+static ZOBRIST: [[u64; 64]; 12] = zobrist_keys();
+const OPPONENT_TO_MOVE: u64 = splitmix64(0xC0FFEE);
+
+fn piece_slot(piece: &ChessPiece) -> usize {
+    let kind = match piece.piece_type {
+        ChessPieceType::Pawn => 0,
+        ChessPieceType::Bishop => 1,
+        ChessPieceType::Knight => 2,
+        ChessPieceType::Rook => 3,
+        ChessPieceType::Queen => 4,
+        ChessPieceType::King => 5,
+    };
+    match piece.player {
+        Color::White => kind,
+        Color::Black => kind + 6,
+    }
+}
+
+/// Zobrist hash of the position with `player` to move.
+pub fn hash(board: &Board, player: &Color) -> u64 {
+    let pieces = board.0.iter().filter_map(|field| {
+        field
+            .piece
+            .map(|piece| ZOBRIST[piece_slot(&piece)][field.coordinates.index() as usize])
+    });
+    let side = match player {
+        Color::White => 0,
+        Color::Black => OPPONENT_TO_MOVE,
+    };
+    pieces.fold(side, |hash, key| hash ^ key)
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub struct Entry {
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+#[derive(Default)]
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Hash table shared by all search threads without locking. Each slot keeps
+/// `hash ^ data` next to `data`, so a slot torn by two racing writers simply
+/// fails the check on the next probe instead of returning garbage.
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> Self {
+        let count = (megabytes.max(1) << 20) / std::mem::size_of::<Slot>();
+        TranspositionTable {
+            slots: (0..count).map(|_| Slot::default()).collect(),
+        }
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<Entry> {
+        let slot = self.slot(hash);
+        let data = slot.data.load(Ordering::Relaxed);
+        let check = slot.check.load(Ordering::Relaxed);
+        (data != 0 && check ^ data == hash).then(|| unpack(data))
+    }
+
+    pub fn store(&self, hash: u64, entry: Entry) {
+        let slot = self.slot(hash);
+        let data = pack(entry);
+        slot.check.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    fn slot(&self, hash: u64) -> &Slot {
+        &self.slots[(hash % self.slots.len() as u64) as usize]
+    }
+}
+
+// Layout: move (16 bits) | score (16) | depth (8) | bound (8). The top bit of
+// the move marks it as present, and the bound is never 0, so neither is a used slot.
+fn pack(entry: Entry) -> u64 {
+    let best_move = entry.best_move.map_or(0, |mv| {
+        let promotion = mv
+            .promotion
+            .and_then(|promotion| PROMOTIONS.iter().position(|p| *p == promotion))
+            .map_or(0, |position| position as u64 + 1);
+        1 << 15 | promotion << 12 | (mv.to.index() as u64) << 6 | mv.from.index() as u64
+    });
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    best_move | (entry.score as i16 as u16 as u64) << 16 | (entry.depth as u64) << 32 | bound << 40
+}
+
+fn unpack(data: u64) -> Entry {
+    let best_move = (data & 1 << 15 != 0).then(|| Move {
+        from: BoardCoordinates::from_index((data & 63) as u32).unwrap(),
+        to: BoardCoordinates::from_index((data >> 6 & 63) as u32).unwrap(),
+        promotion: match data >> 12 & 7 {
+            0 => None,
+            position => Some(PROMOTIONS[position as usize - 1]),
+        },
+    });
+    Entry {
+        depth: (data >> 32) as u8,
+        score: (data >> 16) as u16 as i16 as i32,
+        bound: match data >> 40 & 3 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper,
+        },
+        best_move,
+    }
+}
+
+#[test]
+fn table_round_trip() {
+    let table = TranspositionTable::new(1);
+    let board = Board::default().fill_standard_pieces();
+    let key = hash(&board, &Color::White);
+    assert_ne!(key, hash(&board, &Color::Black));
+    assert_eq!(table.probe(key), None);
+
+    let entry = Entry {
+        depth: 7,
+        score: -29_990,
+        bound: Bound::Lower,
+        best_move: Some(Move {
+            from: BoardCoordinates::from_index(52).unwrap(),
+            to: BoardCoordinates::from_index(60).unwrap(),
+            promotion: Some(ChessPieceType::Knight),
+        }),
+    };
+    table.store(key, entry);
+    assert_eq!(table.probe(key), Some(entry));
+    assert_eq!(table.probe(key ^ 1), None);
+}
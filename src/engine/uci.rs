@@ -0,0 +1,520 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    clock::{Clock, TimeControl},
+    controller::PlayerController,
+    evaluation::MATE,
+    game_state::GameState,
+    moves::Move,
+    search::{Engine, SearchOptions, SearchResult},
+    skill::{Skill, MAX_LEVEL},
+    Color,
+};
+use crate::{
+    board::fen::{from_fen, to_fen, STARTING_FEN},
+    interface::console::outln,
+};
+
+/// Speaks UCI on `input` and `output`, so that GUIs and match runners can
+/// use this program as an engine. A search still running when the input ends
+/// is finished first.
+pub fn serve(input: impl BufRead, output: impl Write + Send) {
+    let output = Mutex::new(output);
+    let say = |line: &str| {
+        let mut output = output.lock().unwrap();
+        let _ = writeln!(output, "{}", line);
+        let _ = output.flush();
+    };
+    let mut options = SearchOptions::default();
+    let mut engine = Arc::new(Engine::new(options));
+    let mut game_state = GameState::start(TimeControl::Unlimited);
+
+    thread::scope(|scope| {
+        let mut search: Option<thread::ScopedJoinHandle<()>> = None;
+        for line in input.lines() {
+            let Ok(line) = line else { break };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some(command) = words.first() else {
+                continue;
+            };
+            if matches!(*command, "stop" | "quit") {
+                engine.stop_handle().store(true, Ordering::Relaxed);
+            }
+            if *command != "isready" {
+                if let Some(search) = search.take() {
+                    let _ = search.join();
+                }
+            }
+            match *command {
+                "uci" => {
+                    say("id name Chess");
+                    say("id author Hiborei");
+                    say(&format!(
+                        "option name Threads type spin default {} min 1 max 256",
+                        options.threads
+                    ));
+                    say(&format!(
+                        "option name Hash type spin default {} min 1 max 4096",
+                        options.hash_megabytes
+                    ));
+                    say(&format!(
+                        "option name Skill Level type spin default {0} min 1 max {0}",
+                        MAX_LEVEL
+                    ));
+                    say("uciok");
+                }
+                "isready" => say("readyok"),
+                "setoption" => match set_option(&mut options, &words) {
+                    Ok(()) => engine = Arc::new(Engine::new(options)),
+                    Err(error) => say(&format!("info string {}", error)),
+                },
+                "ucinewgame" => engine = Arc::new(Engine::new(options)),
+                "position" => match position(&words) {
+                    Ok(position) => game_state = position,
+                    Err(error) => say(&format!("info string {}", error)),
+                },
+                "go" => {
+                    if let Some(engine) = Arc::get_mut(&mut engine) {
+                        engine.options = go_options(options, &words, game_state.current_player);
+                    }
+                    engine.stop_handle().store(false, Ordering::Relaxed);
+                    let engine = Arc::clone(&engine);
+                    let (board, player) = (game_state.board.clone(), game_state.current_player);
+                    let say = &say;
+                    search = Some(scope.spawn(move || {
+                        let result = engine.search(&board, player);
+                        say(&info(&result));
+                        say(&format!(
+                            "bestmove {}",
+                            result.best_move.map_or("0000".to_string(), |mv| mv.uci())
+                        ));
+                    }));
+                }
+                "quit" => break,
+                _ => {}
+            }
+        }
+    });
+}
+
+fn set_option(options: &mut SearchOptions, words: &[&str]) -> Result<(), String> {
+    let line = words.join(" ");
+    let (name, value) = line
+        .strip_prefix("setoption name ")
+        .and_then(|rest| rest.split_once(" value "))
+        .ok_or_else(|| format!("Could not understand: {}", line))?;
+    let invalid = |_| format!("Invalid value for {}: {}", name, value);
+    match name.to_ascii_lowercase().as_str() {
+        "threads" => options.threads = value.parse().map_err(invalid)?,
+        "hash" => options.hash_megabytes = value.parse().map_err(invalid)?,
+        "skill level" => options.skill = value.parse::<Skill>()?,
+        _ => return Err(format!("Unknown option: {}", name)),
+    }
+    Ok(())
+}
+
+/// `position startpos|fen <fen> [moves e2e3 ...]`
+fn position(words: &[&str]) -> Result<GameState, String> {
+    let moves = words.iter().position(|word| *word == "moves");
+    let mut game_state = match words.get(1) {
+        Some(&"startpos") => GameState::start(TimeControl::Unlimited),
+        Some(&"fen") => {
+            let fen = words[2..moves.unwrap_or(words.len())].join(" ");
+            let (board, player) = from_fen(&fen)?;
+            GameState::from_position(board, player, TimeControl::Unlimited)
+        }
+        _ => return Err(format!("Could not understand: {}", words.join(" "))),
+    };
+    for mv in words
+        .iter()
+        .skip(moves.map_or(words.len(), |moves| moves + 1))
+    {
+        game_state = game_state.play(Move::from_uci(mv)?)?.switch_player();
+    }
+    Ok(game_state)
+}
+
+/// The `position` command that sets up `game_state` for another engine.
+pub fn position_command(game_state: &GameState) -> String {
+    let start = to_fen(&game_state.start_board, game_state.start_player, 1);
+    let mut position = if start == STARTING_FEN {
+        "position startpos".to_string()
+    } else {
+        format!("position fen {}", start)
+    };
+    if !game_state.history.is_empty() {
+        position.push_str(" moves");
+        for mv in &game_state.history {
+            position.push(' ');
+            position.push_str(&mv.uci());
+        }
+    }
+    position
+}
+
+/// Turns the limits of a `go` command into search options. With a clock the
+/// engine thinks for a twentieth of its time, or its share until the next
+/// time control, plus half the increment.
+fn go_options(base: SearchOptions, words: &[&str], player: Color) -> SearchOptions {
+    let value = |name: &str| {
+        words
+            .iter()
+            .position(|word| *word == name)
+            .and_then(|position| words.get(position + 1))
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    let (time, increment) = match player {
+        Color::White => (value("wtime"), value("winc")),
+        Color::Black => (value("btime"), value("binc")),
+    };
+    let movetime = value("movetime").or_else(|| {
+        time.map(|time| time / value("movestogo").unwrap_or(20).max(1) + increment.unwrap_or(0) / 2)
+    });
+    let unlimited = words.contains(&"infinite")
+        || (value("depth").is_none() && (movetime.is_some() || value("nodes").is_some()));
+    SearchOptions {
+        depth: value("depth").map_or(if unlimited { 64 } else { base.depth }, |depth| {
+            depth as u32
+        }),
+        nodes: value("nodes"),
+        movetime: match movetime {
+            Some(movetime) => Some(Duration::from_millis(movetime)),
+            None if words.len() > 1 => None,
+            None => base.movetime,
+        },
+        ..base
+    }
+}
+
+fn info(result: &SearchResult) -> String {
+    let score = if result.score.abs() >= MATE - 64 {
+        let plies = MATE - result.score.abs();
+        format!("mate {}", result.score.signum() * (plies + 1) / 2)
+    } else {
+        format!("cp {}", result.score)
+    };
+    format!(
+        "info depth {} score {} nodes {}",
+        result.depth, score, result.nodes
+    )
+}
+
+/// An engine running as a separate program, spoken to over UCI.
+pub struct UciEngine {
+    pub name: String,
+    /// How the engine was started.
+    pub command: String,
+    /// The last `info` line with a score, without its principal variation.
+    pub last_info: Option<String>,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UciEngine {
+    /// Starts `command` (a program and its arguments separated by spaces)
+    /// and waits until it is ready.
+    pub fn spawn(command: &str) -> Result<Self, String> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or("The engine command is empty")?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| format!("Could not start {}: {}", command, error))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UciEngine {
+            name: command.to_string(),
+            command: command.to_string(),
+            last_info: None,
+            child,
+            stdin,
+            lines,
+        };
+        engine.send("uci")?;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let line = engine.next_line(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.new_game()?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| format!("{} has exited", self.name))
+    }
+
+    fn next_line(&mut self, deadline: Instant) -> Result<String, String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(format!("{} did not answer in time", self.name)),
+            Err(RecvTimeoutError::Disconnected) => Err(format!("{} has exited", self.name)),
+        }
+    }
+
+    pub fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while self.next_line(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// Asks for the best move in `game_state`, with `go` holding the search
+    /// limits, e.g. `depth 4`.
+    pub fn best_move(
+        &mut self,
+        game_state: &GameState,
+        go: &str,
+        timeout: Duration,
+    ) -> Result<Move, String> {
+        self.send(&position_command(game_state))?;
+        self.send(&format!("go {}", go))?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let line = match self.next_line(deadline) {
+                Ok(line) => line,
+                Err(error) => {
+                    let _ = self.send("stop");
+                    return Err(error);
+                }
+            };
+            if let Some(best) = line.strip_prefix("bestmove") {
+                let best = best.split_whitespace().next().unwrap_or_default();
+                return Move::from_uci(best).map_err(|error| format!("{}: {}", self.name, error));
+            }
+            if let Some(info) = line
+                .strip_prefix("info ")
+                .filter(|info| info.contains("score "))
+            {
+                let info = info.split(" pv ").next().unwrap_or(info);
+                self.last_info = Some(info.trim().to_string());
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Plays the moves of an external engine. With a clock the engine is told
+/// the time left on both clocks, otherwise it searches with the limits in
+/// `go`. It resigns if it exits or takes far longer than it was asked to.
+pub struct UciController {
+    engine: UciEngine,
+    go: String,
+    timeout: Duration,
+}
+
+impl UciController {
+    pub fn new(engine: UciEngine, go: &str) -> Self {
+        let words: Vec<&str> = go.split_whitespace().collect();
+        let movetime = words
+            .iter()
+            .position(|word| *word == "movetime")
+            .and_then(|position| words.get(position + 1))
+            .and_then(|movetime| movetime.parse::<u64>().ok());
+        UciController {
+            engine,
+            go: go.to_string(),
+            timeout: movetime.map_or(Duration::from_secs(60), |movetime| {
+                Duration::from_millis(2 * movetime + 1000)
+            }),
+        }
+    }
+}
+
+/// The `go` limits for the side to move when playing with `clock`.
+fn clock_limits(clock: &Clock, player: Color) -> String {
+    let mut go = format!(
+        "wtime {} btime {} winc {} binc {}",
+        clock.remaining(Color::White).as_millis(),
+        clock.remaining(Color::Black).as_millis(),
+        clock.increment(Color::White).as_millis(),
+        clock.increment(Color::Black).as_millis()
+    );
+    if let Some(moves) = clock.moves_to_go(player) {
+        go.push_str(&format!(" movestogo {}", moves));
+    }
+    go
+}
+
+impl PlayerController for UciController {
+    fn choose_move(&mut self, game_state: &GameState) -> Option<Move> {
+        let player = game_state.current_player;
+        let (go, timeout) = match &game_state.clock {
+            Some(clock) => (
+                clock_limits(clock, player),
+                clock.remaining(player) + Duration::from_secs(2),
+            ),
+            None => (self.go.clone(), self.timeout),
+        };
+        match self.engine.best_move(game_state, &go, timeout) {
+            Ok(mv) => Some(mv),
+            Err(error) => {
+                outln!("{}, so it resigns.", error);
+                None
+            }
+        }
+    }
+
+    fn info(&self) -> Option<String> {
+        self.engine
+            .last_info
+            .as_ref()
+            .map(|info| format!("last search: {}", info))
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.engine.name)
+    }
+
+    fn setup(&self) -> Option<String> {
+        Some(format!("uci:{}", self.engine.command))
+    }
+}
+
+#[test]
+fn answers_a_scripted_session() {
+    let input = "uci\nsetoption name Threads value 1\nisready\n\
+                 position startpos moves e2e3 e7e6\ngo depth 2\n";
+    let mut output = vec![];
+    serve(input.as_bytes(), &mut output);
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines.contains(&"uciok"));
+    assert!(lines.contains(&"readyok"));
+    assert!(lines.iter().any(|line| line.starts_with("info depth 2 ")));
+    let best = lines.last().unwrap().strip_prefix("bestmove ").unwrap();
+    let game_state = position(&["position", "startpos", "moves", "e2e3", "e7e6"]).unwrap();
+    assert!(game_state.play(Move::from_uci(best).unwrap()).is_ok());
+
+    let words: Vec<&str> = "position fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e3"
+        .split_whitespace()
+        .collect();
+    let game_state = position(&words).unwrap();
+    assert_eq!(game_state.current_player, Color::Black);
+    assert_eq!(position_command(&game_state), words.join(" "));
+}
+
+/// A shell script that pretends to be an engine: it answers the handshake
+/// and then runs `on_go` for every `go`. The script is removed on drop.
+#[cfg(all(test, unix))]
+struct FakeEngine(std::path::PathBuf);
+
+#[cfg(all(test, unix))]
+impl FakeEngine {
+    fn new(name: &str, on_go: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("chess-{}-{}.sh", name, std::process::id()));
+        let script = format!(
+            "while read line; do\n\
+             case \"$line\" in\n\
+             uci) echo 'id name {name}'; echo uciok ;;\n\
+             isready) echo readyok ;;\n\
+             go*) {on_go} ;;\n\
+             quit) exit 0 ;;\n\
+             esac\n\
+             done\n"
+        );
+        std::fs::write(&path, script).unwrap();
+        FakeEngine(path)
+    }
+
+    fn command(&self) -> String {
+        format!("sh {}", self.0.display())
+    }
+}
+
+#[cfg(all(test, unix))]
+impl Drop for FakeEngine {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn drives_a_fake_engine() {
+    use crate::interface::console;
+
+    let game_state = GameState::start(TimeControl::Unlimited);
+    let reply = "echo 'info depth 3 score cp 12 nodes 40 pv e2e3 e7e6'; echo 'bestmove e2e3'";
+    let fake = FakeEngine::new("Fake", reply);
+    let engine = UciEngine::spawn(&fake.command()).unwrap();
+    assert_eq!(engine.name, "Fake");
+    let mut controller = UciController::new(engine, "depth 3");
+    assert_eq!(
+        controller.choose_move(&game_state),
+        Move::from_uci("e2e3").ok()
+    );
+    assert_eq!(
+        controller.info().as_deref(),
+        Some("last search: depth 3 score cp 12 nodes 40")
+    );
+
+    let fake = FakeEngine::new("Crashing", "exit 1");
+    let crashing = UciEngine::spawn(&fake.command()).unwrap();
+    let mut controller = UciController::new(crashing, "depth 3");
+    let output = console::capture("", || {
+        assert_eq!(controller.choose_move(&game_state), None);
+    });
+    assert_eq!(output, "Crashing has exited, so it resigns.\n");
+
+    let fake = FakeEngine::new("Silent", "true");
+    let mut silent = UciEngine::spawn(&fake.command()).unwrap();
+    let error = silent
+        .best_move(&game_state, "depth 3", Duration::from_millis(200))
+        .unwrap_err();
+    assert!(error.contains("did not answer"));
+    let path = fake.0.clone();
+    drop(fake);
+    assert!(!path.exists());
+
+    let clock = Clock::new("40/5+2".parse().unwrap()).unwrap();
+    assert_eq!(
+        clock_limits(&clock, Color::White),
+        "wtime 300000 btime 300000 winc 2000 binc 2000 movestogo 40"
+    );
+}
@@ -0,0 +1,319 @@
+use std::{
+    io::{BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    clock::TimeControl,
+    game_state::GameState,
+    moves::Move,
+    search::{Engine, SearchOptions, SearchResult},
+    Color,
+};
+use crate::board::fen::from_fen;
+
+/// The engine accepts a draw offer when it thinks it is this far behind.
+const ACCEPT_DRAW_BELOW: i32 = -150;
+
+/// Speaks the XBoard/WinBoard protocol (CECP, version 2) on `input` and
+/// `output`, for GUIs that do not support UCI.
+pub fn serve(input: impl BufRead, output: impl Write + Send) {
+    let output = Mutex::new(output);
+    let say = |line: &str| {
+        let mut output = output.lock().unwrap();
+        let _ = writeln!(output, "{}", line);
+        let _ = output.flush();
+    };
+    let mut session = Session::new();
+    // Set when the search is stopped because its move is no longer wanted.
+    let discard = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let mut thinking: Option<thread::ScopedJoinHandle<Option<SearchResult>>> = None;
+        for line in input.lines() {
+            let Ok(line) = line else { break };
+            let line = line.trim();
+            let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+            if let Some(search) = thinking.take() {
+                match command {
+                    // Move now: stop and play the best move found so far.
+                    "?" => session.engine.stop_handle().store(true, Ordering::Relaxed),
+                    "ping" | "time" | "otim" | "post" | "nopost" | "draw" | "hard" | "easy"
+                    | "." => {}
+                    _ => {
+                        discard.store(true, Ordering::Relaxed);
+                        session.engine.stop_handle().store(true, Ordering::Relaxed);
+                    }
+                }
+                if let Ok(Some(result)) = search.join() {
+                    session.engine_moved(result, &say);
+                }
+            }
+            if command == "quit" {
+                break;
+            }
+            session.handle(command, argument.trim(), &say);
+
+            if session.engine_to_move() {
+                discard.store(false, Ordering::Relaxed);
+                session.prepare_search();
+                session.engine.stop_handle().store(false, Ordering::Relaxed);
+                let engine = Arc::clone(&session.engine);
+                let (board, player) = (
+                    session.game_state.board.clone(),
+                    session.game_state.current_player,
+                );
+                let (post, say, discard) = (session.post, &say, &discard);
+                thinking = Some(scope.spawn(move || {
+                    let started = Instant::now();
+                    let result = engine.search(&board, player);
+                    let mv = result.best_move?;
+                    if discard.load(Ordering::Relaxed) {
+                        return None;
+                    }
+                    if post {
+                        say(&format!(
+                            "{} {} {} {} {}",
+                            result.depth,
+                            result.score,
+                            started.elapsed().as_millis() / 10,
+                            result.nodes,
+                            mv.uci()
+                        ));
+                    }
+                    say(&format!("move {}", mv.uci()));
+                    Some(result)
+                }));
+            }
+        }
+        if let Some(search) = thinking {
+            if let Ok(Some(result)) = search.join() {
+                session.engine_moved(result, &say);
+            }
+        }
+    });
+}
+
+struct Session {
+    game_state: GameState,
+    engine: Arc<Engine>,
+    /// The side the engine plays, `None` in force mode.
+    engine_color: Option<Color>,
+    post: bool,
+    /// From `level`: moves per time control (0 for the whole game) and increment.
+    moves_per_session: u32,
+    increment: Duration,
+    /// From `st`: a fixed time for every move.
+    per_move: Option<Duration>,
+    /// From `sd`: the deepest the engine may search.
+    depth: Option<u32>,
+    /// From `time`: what is left on the engine's clock.
+    time: Option<Duration>,
+    last_score: Option<i32>,
+    offered_draw: bool,
+}
+
+impl Session {
+    fn new() -> Self {
+        Session {
+            game_state: GameState::start(TimeControl::Unlimited),
+            engine: Arc::new(Engine::new(SearchOptions::default())),
+            engine_color: Some(Color::Black),
+            post: false,
+            moves_per_session: 0,
+            increment: Duration::ZERO,
+            per_move: None,
+            depth: None,
+            time: None,
+            last_score: None,
+            offered_draw: false,
+        }
+    }
+
+    fn handle(&mut self, command: &str, argument: &str, say: &impl Fn(&str)) {
+        match command {
+            "protover" => say(
+                "feature myname=\"Chess\" usermove=1 setboard=1 ping=1 playother=1 draw=1 \
+                 sigint=0 sigterm=0 colors=0 reuse=1 analyze=0 done=1",
+            ),
+            "new" => {
+                let options = self.engine.options;
+                *self = Session {
+                    post: self.post,
+                    moves_per_session: self.moves_per_session,
+                    increment: self.increment,
+                    per_move: self.per_move,
+                    ..Session::new()
+                };
+                self.engine = Arc::new(Engine::new(options));
+            }
+            "force" | "result" => self.engine_color = None,
+            "go" => self.engine_color = Some(self.game_state.current_player),
+            "playother" => self.engine_color = Some(self.game_state.current_player.switch()),
+            "usermove" => self.user_move(argument, say),
+            "setboard" => match from_fen(argument) {
+                Ok((board, player)) => {
+                    self.game_state =
+                        GameState::from_position(board, player, TimeControl::Unlimited);
+                    self.offered_draw = false;
+                }
+                Err(error) => say(&format!("tellusererror Illegal position: {}", error)),
+            },
+            "level" => match parse_level(argument) {
+                Some((moves, base, increment)) => {
+                    self.moves_per_session = moves;
+                    self.increment = increment;
+                    self.time = Some(base);
+                    self.per_move = None;
+                }
+                None => say(&format!("Error (bad level): {}", argument)),
+            },
+            "st" => match argument.parse::<u64>() {
+                Ok(seconds) => self.per_move = Some(Duration::from_secs(seconds)),
+                Err(_) => say(&format!("Error (bad time): {}", argument)),
+            },
+            "sd" => match argument.parse::<u32>() {
+                Ok(depth) => self.depth = Some(depth.max(1)),
+                Err(_) => say(&format!("Error (bad depth): {}", argument)),
+            },
+            "time" => {
+                if let Ok(centiseconds) = argument.parse::<u64>() {
+                    self.time = Some(Duration::from_millis(centiseconds * 10));
+                }
+            }
+            // The opponent's clock does not change how long the engine thinks.
+            "otim" => {}
+            "undo" => self.game_state = self.game_state.clone().undo(),
+            "remove" => self.game_state = self.game_state.clone().undo().undo(),
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "draw" => {
+                let dead = !self.game_state.board.has_mating_material(&Color::White)
+                    && !self.game_state.board.has_mating_material(&Color::Black);
+                if dead
+                    || self
+                        .last_score
+                        .is_some_and(|score| score <= ACCEPT_DRAW_BELOW)
+                {
+                    say("offer draw");
+                }
+            }
+            "ping" => say(&format!("pong {}", argument)),
+            "xboard" | "accepted" | "rejected" | "random" | "computer" | "hard" | "easy" | "."
+            | "?" | "name" | "rating" | "ics" => {}
+            _ if Move::from_uci(command).is_ok() => self.user_move(command, say),
+            _ => say(&format!("Error (unknown command): {}", command)),
+        }
+    }
+
+    fn user_move(&mut self, text: &str, say: &impl Fn(&str)) {
+        match Move::from_uci(text).and_then(|mv| self.game_state.clone().play(mv)) {
+            Ok(next) => {
+                self.game_state = next.switch_player();
+                self.after_move(say);
+            }
+            Err(_) => say(&format!("Illegal move: {}", text)),
+        }
+    }
+
+    fn engine_moved(&mut self, result: SearchResult, say: &impl Fn(&str)) {
+        let Some(mv) = result.best_move else { return };
+        if let Ok(next) = self.game_state.clone().play(mv) {
+            self.game_state = next.switch_player();
+            self.last_score = Some(result.score);
+            self.after_move(say);
+        }
+    }
+
+    /// Announces the end of the game, or offers a draw once nobody can win.
+    fn after_move(&mut self, say: &impl Fn(&str)) {
+        if let Some(outcome) = self.game_state.outcome_without_moves() {
+            self.game_state.outcome = Some(outcome);
+            say(&format!("{} {{{}}}", outcome.result(), outcome));
+        } else if !self.offered_draw
+            && !self.game_state.board.has_mating_material(&Color::White)
+            && !self.game_state.board.has_mating_material(&Color::Black)
+        {
+            self.offered_draw = true;
+            say("offer draw");
+        }
+    }
+
+    fn engine_to_move(&self) -> bool {
+        self.engine_color == Some(self.game_state.current_player)
+            && self.game_state.outcome.is_none()
+    }
+
+    /// Sets the search limits for the next move from `level`, `st`, `sd`
+    /// and the last `time`.
+    fn prepare_search(&mut self) {
+        let movetime = self.per_move.or_else(|| {
+            let time = self.time?;
+            let moves_left = match self.moves_per_session {
+                0 => 30,
+                moves => moves - (self.game_state.history.len() as u32 / 2) % moves,
+            };
+            Some((time / moves_left + self.increment * 3 / 4).min(time / 2))
+        });
+        let base = SearchOptions::default();
+        let options = SearchOptions {
+            depth: self
+                .depth
+                .unwrap_or(if movetime.is_some() { 64 } else { base.depth }),
+            movetime: movetime.or(if self.depth.is_some() {
+                None
+            } else {
+                base.movetime
+            }),
+            ..self.engine.options
+        };
+        match Arc::get_mut(&mut self.engine) {
+            Some(engine) => engine.options = options,
+            None => self.engine = Arc::new(Engine::new(options)),
+        }
+    }
+}
+
+/// `level MPS BASE INC`, where BASE is minutes or minutes:seconds.
+fn parse_level(argument: &str) -> Option<(u32, Duration, Duration)> {
+    let words: Vec<&str> = argument.split_whitespace().collect();
+    let [moves, base, increment] = words[..] else {
+        return None;
+    };
+    let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+    let base = minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?;
+    Some((
+        moves.parse().ok()?,
+        Duration::from_secs(base),
+        Duration::from_secs_f64(increment.parse().ok()?),
+    ))
+}
+
+#[test]
+fn plays_a_scripted_session() {
+    let input = "xboard\nprotover 2\nnew\nsd 2\npost\nusermove e2e3\nping 1\n\
+                 usermove e2e4\nremove\nforce\nusermove e2e3\nsetboard 8/8/8/8/8/8/k7/2K5 b - - 0 1\n\
+                 go\n";
+    let mut output = vec![];
+    serve(input.as_bytes(), &mut output);
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[0].starts_with("feature ") && lines[0].ends_with("done=1"));
+    let reply = lines
+        .iter()
+        .position(|line| line.starts_with("move "))
+        .unwrap();
+    assert!(lines[reply - 1].starts_with("2 "));
+    assert_eq!(lines[reply + 1], "pong 1");
+    assert_eq!(lines[reply + 2], "Illegal move: e2e4");
+    // After taking back both moves White's e-pawn can move again.
+    assert!(!lines.contains(&"Illegal move: e2e3"));
+    assert!(lines[reply + 4].starts_with("move "));
+    assert_eq!(lines[reply + 5], "offer draw");
+    assert!(parse_level("40 0:30 0").is_some_and(|level| level.1 == Duration::from_secs(30)));
+}
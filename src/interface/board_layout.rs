@@ -1,54 +1,231 @@
-use std::io::{self, Write};
-
 use crate::{
     board::{
+        chesspiece::ChessPieceType,
         field::Field,
         layout::{Board, BoardCoordinates},
     },
-    engine::Player,
+    engine::{
+        game_state::{check_if_king_in_check, GameState},
+        moves::Move,
+        Color,
+    },
 };
 
 extern crate termion;
 
-use termion::color;
+use super::{
+    console::{self, out, outln},
+    theme::{self, Shade, Theme},
+};
 
 pub trait DrawInTerminal {
     fn draw(&self);
 }
 
+/// The row with the file letters above the board, as `game_engine` draws
+/// it under the clocks.
+pub const BOARD_TOP: u16 = 3;
+
+/// The board as seen by whoever plays `bottom`.
+pub struct BoardView<'a> {
+    pub board: &'a Board,
+    pub bottom: Color,
+    pub highlights: Highlights,
+}
+
+/// Fields drawn on a background of their own rather than the checkered one.
+#[derive(Default, Clone)]
+pub struct Highlights {
+    pub cursor: Option<BoardCoordinates>,
+    pub selected: Option<BoardCoordinates>,
+    /// Where the selected piece can go.
+    pub destinations: Vec<BoardCoordinates>,
+    /// The fields of a suggested move.
+    pub hint: Vec<BoardCoordinates>,
+    pub last_move: Option<Move>,
+    /// The king of the side to move, if it is in check.
+    pub check: Option<BoardCoordinates>,
+}
+
+impl Highlights {
+    /// The last move and a king in check.
+    pub fn of(game_state: &GameState) -> Self {
+        let player = game_state.current_player;
+        let check = game_state
+            .board
+            .get_all_fields_by_player(&player)
+            .into_iter()
+            .find(|field| field.piece.map(|piece| piece.piece_type) == Some(ChessPieceType::King))
+            .map(|field| field.coordinates)
+            .filter(|_| check_if_king_in_check(&game_state.board, &player));
+        Highlights {
+            last_move: game_state.history.last().copied(),
+            check,
+            ..Default::default()
+        }
+    }
+
+    /// Marks `from` as selected, along with where the `legal` moves from it go.
+    pub fn selecting(self, from: Option<BoardCoordinates>, legal: &[Move]) -> Self {
+        Highlights {
+            selected: from,
+            destinations: legal
+                .iter()
+                .filter(|mv| Some(mv.from) == from)
+                .map(|mv| mv.to)
+                .collect(),
+            ..self
+        }
+    }
+
+    fn background(&self, field: BoardCoordinates, theme: &Theme) -> Shade {
+        let last_move = self
+            .last_move
+            .is_some_and(|mv| mv.from == field || mv.to == field);
+        if self.cursor == Some(field) {
+            theme.cursor
+        } else if self.selected == Some(field) {
+            theme.selected
+        } else if self.destinations.contains(&field) {
+            theme.destination
+        } else if self.hint.contains(&field) {
+            theme.hint
+        } else if self.check == Some(field) {
+            theme.check
+        } else if last_move {
+            theme.last_move
+        } else if !(field.index() / 8 + field.index() % 8).is_multiple_of(2) {
+            // The dark fields, like the one in White's left corner.
+            theme.dark
+        } else {
+            theme.light
+        }
+    }
+}
+
+/// The row and column, counted from the top left of the board, at which
+/// `field` is drawn when `bottom` sits at the bottom.
+pub fn screen_position(field: BoardCoordinates, bottom: Color) -> (u16, u16) {
+    let shown = field.oriented(bottom).index() as u16;
+    (shown / 8, shown % 8)
+}
+
 impl DrawInTerminal for Board {
     fn draw(&self) {
-        println!();
-        print!("  {}A|B|C|D|E|F|G|H|", color::Fg(color::White));
+        BoardView {
+            board: self,
+            bottom: Color::White,
+            highlights: Highlights::default(),
+        }
+        .draw()
+    }
+}
+
+impl DrawInTerminal for BoardView<'_> {
+    fn draw(&self) {
+        let field_at = |x, y| {
+            BoardCoordinates::from_coordinates(x, y)
+                .unwrap()
+                .oriented(self.bottom)
+        };
+        let files: String = (0..8)
+            .map(|y| format!("{}|", field_at(0, y).file()))
+            .collect();
+        let text = theme::current().fg(theme::current().text);
+        outln!();
+        out!("  {}{}", text, files);
         for x in 0..8 {
-            println!();
+            outln!();
 
-            print!("{}|", x + 1);
+            out!("{}|", field_at(x, 0).rank());
 
             for y in 0..8 {
-                self.at(&BoardCoordinates::from_coordinates(x, y).unwrap())
-                    .draw();
-                print!("{}|", color::Fg(color::White));
-                io::stdout().flush().unwrap();
+                self.draw_field(field_at(x, y));
+                out!("{}|", text);
+                console::flush();
             }
-            print!("{}{}", color::Fg(color::White), x + 1);
+            out!("{}{}", text, field_at(x, 0).rank());
         }
-        println!();
-        print!("  {}A|B|C|D|E|F|G|H|", color::Fg(color::White));
-        println!();
+        outln!();
+        out!("  {}{}", text, files);
+        outln!();
+    }
+}
+
+impl BoardView<'_> {
+    /// The rows the board takes: the ranks with the files above and below.
+    pub const HEIGHT: u16 = 10;
+
+    /// Draws a single field with its background where the cursor is.
+    pub fn draw_field(&self, field: BoardCoordinates) {
+        let theme = theme::current();
+        out!("{}", theme.bg(self.highlights.background(field, theme)));
+        self.board.at(&field).draw();
+        out!("{}", theme.bg(Shade::Default));
     }
 }
 
 impl DrawInTerminal for Field {
     fn draw(&self) {
-        if let Some(piece) = self.piece {
-            if matches!(piece.player, Player::User) {
-                print!("{}{}", color::Fg(color::Blue), piece.piece_type)
-            } else {
-                print!("{}{}", color::Fg(color::Red), piece.piece_type)
-            };
-        } else {
-            print!(" ");
+        match self.piece {
+            Some(piece) => out!("{}", theme::current().piece(piece)),
+            None => out!(" "),
         }
     }
 }
+
+#[test]
+fn highlights_check_and_last_move() {
+    use crate::{board::fen::from_fen, engine::clock::TimeControl};
+
+    let (board, player) = from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+    let game_state = GameState::from_position(board, player, TimeControl::Unlimited)
+        .play(Move::from_uci("h1h8").unwrap())
+        .unwrap()
+        .switch_player();
+    let highlights = Highlights::of(&game_state);
+    let field = |name: &str| name.parse::<BoardCoordinates>().unwrap();
+    assert_eq!(highlights.check, Some(field("e8")));
+    assert_eq!(highlights.last_move.map(|mv| mv.to), Some(field("h8")));
+    assert_eq!(
+        highlights.background(field("a1"), &Theme::default()),
+        highlights.background(field("c3"), &Theme::default())
+    );
+    assert_ne!(
+        highlights.background(field("a1"), &Theme::default()),
+        highlights.background(field("a2"), &Theme::default())
+    );
+}
+
+#[test]
+fn draws_the_board_for_black() {
+    use super::console::capture;
+
+    let board = crate::engine::moves::apply_move(
+        Board::default().fill_standard_pieces(),
+        &Move::from_uci("e2e3").unwrap(),
+    );
+    let output = capture("", || {
+        BoardView {
+            board: &board,
+            bottom: Color::Black,
+            highlights: Highlights::default(),
+        }
+        .draw()
+    });
+    let expected = [
+        "",
+        "  h|g|f|e|d|c|b|a|",
+        "1|R|N|B|K|Q|B|N|R|1",
+        "2|P|P|P| |P|P|P|P|2",
+        "3| | | |P| | | | |3",
+        "4| | | | | | | | |4",
+        "5| | | | | | | | |5",
+        "6| | | | | | | | |6",
+        "7|p|p|p|p|p|p|p|p|7",
+        "8|r|n|b|k|q|b|n|r|8",
+        "  h|g|f|e|d|c|b|a|",
+        "",
+    ];
+    assert_eq!(output, expected.join("\n"));
+}
@@ -0,0 +1,429 @@
+/// What the program was started to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Play,
+    Analyze,
+    Review,
+    Perft,
+    Bench,
+    Match,
+    Sprt,
+    Serve,
+    Uci,
+    Xboard,
+}
+
+/// An option and, if it takes one, what its value looks like.
+struct Opt {
+    name: &'static str,
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+const fn flag(name: &'static str, help: &'static str) -> Opt {
+    Opt {
+        name,
+        value: None,
+        help,
+    }
+}
+
+const fn valued(name: &'static str, value: &'static str, help: &'static str) -> Opt {
+    Opt {
+        name,
+        value: Some(value),
+        help,
+    }
+}
+
+struct CommandSpec {
+    command: Command,
+    name: &'static str,
+    summary: &'static str,
+    /// The argument that may follow the command without its option name,
+    /// e.g. the file in `analyze game.pgn`.
+    positional: Option<&'static str>,
+    /// The option that used to start this mode before there were commands,
+    /// e.g. `--match 10`.
+    legacy: Option<&'static str>,
+    options: &'static [&'static [Opt]],
+}
+
+const THEME: &[Opt] = &[
+    valued("--theme", "<name>", "classic, ocean, wood or mono"),
+    valued(
+        "--glyphs",
+        "<ascii|unicode>",
+        "Letters or chess symbols for the pieces",
+    ),
+    valued(
+        "--config",
+        "<file>",
+        "Read the settings above from this file",
+    ),
+];
+
+const ENGINE: &[Opt] = &[
+    valued("--depth", "<plies>", "How deep the engine searches"),
+    valued(
+        "--threads",
+        "<count>",
+        "How many threads the engine searches with",
+    ),
+];
+
+const PLAY: &[Opt] = &[
+    valued(
+        "--color",
+        "<white|black>",
+        "Your colour against the computer",
+    ),
+    valued(
+        "--mode",
+        "<human|engine|hotseat>",
+        "You against the computer, the engine against itself, or two people",
+    ),
+    flag("--hotseat", "The same as --mode hotseat"),
+    valued(
+        "--white",
+        "<player>",
+        "human[:name], random, engine[:settings], script:<file> or uci:<command>",
+    ),
+    valued("--black", "<player>", "The same for Black"),
+    flag(
+        "--engine",
+        "Play against this program's engine, not random moves",
+    ),
+    valued("--skill", "<1-20>", "The engine's level"),
+    valued("--elo", "<rating>", "The engine's level as a rating"),
+    valued(
+        "--uci-engine",
+        "<command>",
+        "Play against an external UCI engine",
+    ),
+    valued(
+        "--go",
+        "<limits>",
+        "The UCI engine's limits without a clock",
+    ),
+    valued("--fen", "<position>", "Start from this position"),
+    valued("--pgn", "<file>", "Carry on from the end of this game"),
+    valued("--time", "<control>", "The time control, e.g. 5+3 or none"),
+    valued(
+        "--seed",
+        "<number>",
+        "Make the same random choices as before",
+    ),
+    valued("--resume", "<file>", "Carry on with a saved game"),
+    flag("--flip", "Turn the board to the side to move"),
+    flag("--line", "Type the fields instead of using the cursor"),
+    valued(
+        "--script",
+        "<file>",
+        "Read what would be typed from the file",
+    ),
+    flag(
+        "--analyze",
+        "Have this program's engine go over the game at the end",
+    ),
+    valued("--save-pgn", "<file>", "Save the game as PGN"),
+    valued(
+        "--host",
+        "<address>",
+        "Wait for an opponent on the network, who may reconnect within 2 minutes",
+    ),
+    valued(
+        "--connect",
+        "<address>",
+        "Play the opponent waiting there, reconnecting within 2 minutes",
+    ),
+    valued("--name", "<name>", "Who you are to the other side"),
+    valued("--broadcast", "<address>", "Let others follow the game"),
+];
+
+const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        command: Command::Play,
+        name: "play",
+        summary: "Play a game in the terminal (the default)",
+        positional: None,
+        legacy: None,
+        options: &[PLAY, ENGINE, THEME],
+    },
+    CommandSpec {
+        command: Command::Analyze,
+        name: "analyze",
+        summary: "Analyse a game saved as PGN",
+        positional: Some("--pgn"),
+        legacy: None,
+        options: &[
+            &[
+                valued("--pgn", "<file>", "The game to analyse"),
+                valued("--output", "<file>", "Save the annotated game there"),
+            ],
+            ENGINE,
+            THEME,
+        ],
+    },
+    CommandSpec {
+        command: Command::Review,
+        name: "review",
+        summary: "Draw a graph of the evaluation over a game saved as PGN",
+        positional: Some("--pgn"),
+        legacy: Some("--review"),
+        options: &[
+            &[valued("--pgn", "<file>", "The game to review")],
+            ENGINE,
+            THEME,
+        ],
+    },
+    CommandSpec {
+        command: Command::Perft,
+        name: "perft",
+        summary: "Count the positions a number of moves deep",
+        positional: Some("--depth"),
+        legacy: None,
+        options: &[&[
+            valued("--depth", "<plies>", "How many moves deep"),
+            valued("--fen", "<position>", "Count from this position"),
+        ]],
+    },
+    CommandSpec {
+        command: Command::Bench,
+        name: "bench",
+        summary: "Time the engine on a few positions",
+        positional: None,
+        legacy: None,
+        options: &[ENGINE],
+    },
+    CommandSpec {
+        command: Command::Match,
+        name: "match",
+        summary: "Play engine settings against each other",
+        positional: Some("--match"),
+        legacy: Some("--match"),
+        options: &[&[
+            valued("--match", "<games>", "How many games to play"),
+            valued("--first", "<contestant>", "random or engine[:settings]"),
+            valued("--second", "<contestant>", "The same for the other side"),
+            valued("--openings", "<file>", "One opening per line"),
+            valued("--max-moves", "<moves>", "Draw games that go on longer"),
+            valued("--save-pgn", "<file>", "Save the games as PGN"),
+            valued("--seed", "<number>", "Make the same random moves as before"),
+        ]],
+    },
+    CommandSpec {
+        command: Command::Sprt,
+        name: "sprt",
+        summary: "Test whether one UCI engine is stronger than another",
+        positional: None,
+        legacy: Some("--sprt"),
+        options: &[&[
+            flag("--sprt", "The same as the command"),
+            valued("--first", "<command>", "The engine to test"),
+            valued("--second", "<command>", "The engine to test against"),
+            valued("--elo0", "<elo>", "The difference to reject"),
+            valued("--elo1", "<elo>", "The difference to accept"),
+            valued("--alpha", "<chance>", "Of accepting wrongly"),
+            valued("--beta", "<chance>", "Of rejecting wrongly"),
+            valued("--games", "<games>", "The most games to play"),
+            valued("--concurrency", "<games>", "How many games at once"),
+            valued("--go", "<limits>", "The limits for every move"),
+            valued("--openings", "<file>", "One opening per line"),
+            valued("--max-moves", "<moves>", "Draw games that go on longer"),
+        ]],
+    },
+    CommandSpec {
+        command: Command::Serve,
+        name: "serve",
+        summary: "Run the game server",
+        positional: Some("--serve"),
+        legacy: Some("--serve"),
+        options: &[&[
+            valued("--serve", "<address>", "Where to listen"),
+            valued("--store", "<directory>", "Keep the games there"),
+        ]],
+    },
+    CommandSpec {
+        command: Command::Uci,
+        name: "uci",
+        summary: "Speak UCI on standard input and output",
+        positional: None,
+        legacy: Some("--uci"),
+        options: &[&[flag("--uci", "The same as the command")]],
+    },
+    CommandSpec {
+        command: Command::Xboard,
+        name: "xboard",
+        summary: "Speak the XBoard protocol on standard input and output",
+        positional: None,
+        legacy: Some("--xboard"),
+        options: &[&[flag("--xboard", "The same as the command")]],
+    },
+];
+
+/// The command and its options, checked against what the command takes.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cli {
+    pub command: Command,
+    /// The options with their values, e.g. `["--depth", "3", "--flip"]`,
+    /// with the positional argument under its option name.
+    pub args: Vec<String>,
+    /// Whether `--help` was asked for instead.
+    pub help: bool,
+}
+
+fn spec(command: Command) -> &'static CommandSpec {
+    COMMANDS
+        .iter()
+        .find(|spec| spec.command == command)
+        .unwrap()
+}
+
+/// Reads `chess [command] [options]`. Without a command the options say
+/// what to do, as they did before there were commands, and otherwise it is
+/// a game.
+pub fn parse(args: &[String]) -> Result<Cli, String> {
+    let help = args.iter().any(|arg| arg == "--help" || arg == "-h");
+    let mut args: Vec<&String> = args
+        .iter()
+        .filter(|arg| *arg != "--help" && *arg != "-h")
+        .collect();
+    let named = args
+        .first()
+        .and_then(|first| COMMANDS.iter().find(|spec| spec.name == first.as_str()));
+    let spec = match named {
+        Some(spec) => {
+            args.remove(0);
+            spec
+        }
+        None if args.first().is_some_and(|first| *first == "help") => {
+            let topic = args.get(1).map(|name| name.as_str()).unwrap_or("play");
+            let spec = COMMANDS
+                .iter()
+                .find(|spec| spec.name == topic)
+                .ok_or_else(|| format!("Unknown command: {}", topic))?;
+            return Ok(Cli {
+                command: spec.command,
+                args: vec![],
+                help: true,
+            });
+        }
+        None => COMMANDS
+            .iter()
+            .find(|spec| {
+                spec.legacy
+                    .is_some_and(|legacy| args.iter().any(|arg| *arg == legacy))
+            })
+            .unwrap_or(spec(Command::Play)),
+    };
+
+    let mut parsed = vec![];
+    let mut positional = spec.positional;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let option = spec
+            .options
+            .iter()
+            .flat_map(|group| group.iter())
+            .find(|option| option.name == arg);
+        match (option, positional) {
+            (Some(option), _) => {
+                parsed.push(arg.clone());
+                if let Some(value) = option.value {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("{} needs a value: {}", arg, value))?;
+                    parsed.push(value.clone());
+                }
+                if Some(option.name) == positional {
+                    positional = None;
+                }
+            }
+            // Like `--review <file>`, which only names the command.
+            (None, _) if Some(arg.as_str()) == spec.legacy => {}
+            (None, _) if arg.starts_with('-') => {
+                return Err(format!("Unknown option for {}: {}", spec.name, arg))
+            }
+            (None, Some(name)) => {
+                parsed.extend([name.to_string(), arg.clone()]);
+                positional = None;
+            }
+            (None, None) => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+    Ok(Cli {
+        command: spec.command,
+        args: parsed,
+        help,
+    })
+}
+
+/// What `--help` prints: the commands, and the options of `command`.
+pub fn usage(command: Command) -> String {
+    let spec = spec(command);
+    let mut usage = match (command, spec.positional) {
+        (Command::Play, _) => "Usage: chess [command] [options]\n\nCommands:\n".to_string(),
+        (_, Some(positional)) => {
+            let placeholder = spec
+                .options
+                .iter()
+                .flat_map(|group| group.iter())
+                .find(|option| option.name == positional)
+                .and_then(|option| option.value)
+                .unwrap_or_default();
+            format!(
+                "Usage: chess {} {} [options]\n\n{}.\n",
+                spec.name, placeholder, spec.summary
+            )
+        }
+        (_, None) => format!(
+            "Usage: chess {} [options]\n\n{}.\n",
+            spec.name, spec.summary
+        ),
+    };
+    if command == Command::Play {
+        for spec in COMMANDS {
+            usage.push_str(&format!("  {:<10}{}\n", spec.name, spec.summary));
+        }
+        usage.push_str("\nRun chess help <command> for the options of the others.\n");
+    }
+    usage.push_str("\nOptions:\n");
+    for option in spec.options.iter().flat_map(|group| group.iter()) {
+        let name = match option.value {
+            Some(value) => format!("{} {}", option.name, value),
+            None => option.name.to_string(),
+        };
+        usage.push_str(&format!("  {:<32}{}\n", name, option.help));
+    }
+    usage.push_str(&format!("  {:<32}{}\n", "-h, --help", "Show this help"));
+    usage
+}
+
+#[test]
+fn parses_commands_and_options() {
+    let parse = |line: &str| {
+        let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        parse(&args)
+    };
+    let cli = parse("analyze game.pgn --depth 4").unwrap();
+    assert_eq!(cli.command, Command::Analyze);
+    assert_eq!(cli.args, ["--pgn", "game.pgn", "--depth", "4"]);
+
+    // Modes picked with an option before there were commands.
+    let cli = parse("--first engine --match 10").unwrap();
+    assert_eq!(cli.command, Command::Match);
+    assert_eq!(cli.args, ["--first", "engine", "--match", "10"]);
+    let cli = parse("--review game.pgn").unwrap();
+    assert_eq!(cli.command, Command::Review);
+    assert_eq!(cli.args, ["--pgn", "game.pgn"]);
+    assert_eq!(parse("").unwrap().command, Command::Play);
+    assert!(parse("perft -h").unwrap().help);
+    assert_eq!(parse("help bench").unwrap().command, Command::Bench);
+
+    assert!(parse("play --depth").is_err());
+    assert!(parse("play --store games").is_err());
+    assert!(parse("perft 3 4").is_err());
+    assert!(parse("help nothing").is_err());
+    assert!(usage(Command::Perft).starts_with("Usage: chess perft <plies> [options]"));
+}
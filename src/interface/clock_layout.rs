@@ -0,0 +1,116 @@
+use std::{
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use termion::{clear, cursor};
+
+use super::{
+    board_layout::DrawInTerminal,
+    console::{self, outln},
+    theme, tui,
+};
+use crate::engine::{clock::Clock, Color};
+
+impl DrawInTerminal for Clock {
+    fn draw(&self) {
+        outln!("{}", line(self));
+    }
+}
+
+/// Both clocks, with a marker at the one that is running.
+fn line(clock: &Clock) -> String {
+    let mut line = theme::current().fg(theme::current().text);
+    for player in [Color::White, Color::Black] {
+        let marker = if clock.running() == Some(player) {
+            ">"
+        } else {
+            " "
+        };
+        line.push_str(&format!(
+            "{} {}: {}   ",
+            marker,
+            player,
+            format_duration(clock.remaining(player))
+        ));
+    }
+    line
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else if minutes == 0 && seconds < 10 {
+        format!("0:{:02}.{}", seconds, duration.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Keeps the clocks counting down while the user is typing: on the first
+/// line of the terminal, or in the status area of the board the cursor moves
+/// on. Stops when dropped, or once the player to move is out of time.
+pub struct ClockTicker {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ClockTicker {
+    pub fn spawn(clock: Clock) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = console::is_terminal().then(|| {
+            let stop = stop.clone();
+            let row = if tui::enabled() { tui::CLOCK_ROW } else { 1 };
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let out = clock.out_of_time();
+                    let message =
+                        out.map_or(String::new(), |player| format!("{}'s time is up.", player));
+                    // Holding stdout keeps the board from being drawn halfway
+                    // through.
+                    let mut stdout = io::stdout().lock();
+                    let _ = write!(
+                        stdout,
+                        "{}{}{}{}{}{}",
+                        cursor::Save,
+                        cursor::Goto(1, row),
+                        clear::CurrentLine,
+                        line(&clock),
+                        message,
+                        cursor::Restore
+                    );
+                    let _ = stdout.flush();
+                    drop(stdout);
+                    if out.is_some() {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                }
+            })
+        });
+        ClockTicker { stop, handle }
+    }
+}
+
+impl Drop for ClockTicker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[test]
+fn duration_formatting() {
+    assert_eq!(format_duration(Duration::from_secs(5400)), "1:30:00");
+    assert_eq!(format_duration(Duration::from_secs(185)), "3:05");
+    assert_eq!(format_duration(Duration::from_millis(9_450)), "0:09.4");
+}
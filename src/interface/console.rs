@@ -0,0 +1,170 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+/// Where a game reads its commands and writes what it shows, if not the
+/// terminal: a script and a buffer when it is played from a file or a test.
+struct Console {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+}
+
+thread_local! {
+    static CONSOLE: RefCell<Option<Console>> = const { RefCell::new(None) };
+}
+
+/// Reads from `input` and writes to `output` on this thread until the
+/// returned guard is dropped. The lines read are written out after the
+/// prompts, so that the output reads like a session at the terminal.
+pub fn redirect(input: impl BufRead + 'static, output: impl Write + 'static) -> Redirect {
+    let console = Console {
+        input: Box::new(input),
+        output: Box::new(output),
+    };
+    Redirect(CONSOLE.with(|current| current.replace(Some(console))))
+}
+
+/// Puts back the console from before `redirect` when dropped.
+pub struct Redirect(Option<Console>);
+
+impl Drop for Redirect {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CONSOLE.with(|current| {
+            if let Some(mut console) = current.replace(previous) {
+                let _ = console.output.flush();
+            }
+        });
+    }
+}
+
+/// Whether what is written goes straight to a terminal, which can move the
+/// cursor around and keep the clock ticking.
+pub fn is_terminal() -> bool {
+    CONSOLE.with(|current| current.borrow().is_none()) && termion::is_tty(&io::stdout())
+}
+
+/// The next line without its line break, or `None` at the end of the input.
+pub fn read_line() -> Option<String> {
+    let mut line = String::new();
+    let redirected = CONSOLE.with(|current| {
+        current.borrow_mut().as_mut().map(|console| {
+            let read = console.input.read_line(&mut line);
+            if read.as_ref().is_ok_and(|read| *read > 0) {
+                let _ = writeln!(console.output, "{}", line.trim_end());
+            }
+            read
+        })
+    });
+    let read = redirected.unwrap_or_else(|| io::stdin().read_line(&mut line));
+    match read {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+    }
+}
+
+pub fn write(text: fmt::Arguments) {
+    CONSOLE.with(|current| match current.borrow_mut().as_mut() {
+        Some(console) => {
+            let _ = console.output.write_fmt(text);
+        }
+        None => print!("{}", text),
+    });
+}
+
+pub fn flush() {
+    CONSOLE.with(|current| match current.borrow_mut().as_mut() {
+        Some(console) => {
+            let _ = console.output.flush();
+        }
+        None => {
+            let _ = io::stdout().flush();
+        }
+    });
+}
+
+/// Like `print!`, to the console.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::interface::console::write(format_args!($($arg)*))
+    };
+}
+
+/// Like `println!`, to the console.
+macro_rules! outln {
+    () => {
+        $crate::interface::console::write(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {{
+        $crate::interface::console::write(format_args!($($arg)*));
+        $crate::interface::console::write(format_args!("\n"));
+    }};
+}
+
+pub(crate) use {out, outln};
+
+/// A buffer that can be handed to `redirect` and read afterwards.
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct Buffer(std::rc::Rc<RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for Buffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `run` with `script` as its input and returns what it wrote, without
+/// the cursor movements. It draws in the mono theme, so Black's pieces are
+/// lower case.
+#[cfg(test)]
+pub fn capture(script: &str, run: impl FnOnce()) -> String {
+    let buffer = Buffer::default();
+    {
+        let _theme = super::theme::override_with(&super::theme::MONO);
+        let _redirect = redirect(io::Cursor::new(script.to_string()), buffer.clone());
+        run();
+    }
+    let output = String::from_utf8_lossy(&buffer.0.borrow()).into_owned();
+    plain(&output)
+}
+
+/// `text` without terminal escape sequences like `\x1b[38;5;15m`.
+#[cfg(test)]
+fn plain(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+        if char != '\x1b' {
+            plain.push(char);
+        } else if chars.next() == Some('[') {
+            for char in chars.by_ref() {
+                if char.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        }
+    }
+    plain
+}
+
+#[test]
+fn captures_a_session() {
+    let output = capture("first\nsecond\n", || {
+        outln!("Say something");
+        let line = read_line().unwrap();
+        out!("You said {}\x1b[0m", line);
+        outln!();
+        assert_eq!(read_line().as_deref(), Some("second"));
+        assert_eq!(read_line(), None);
+    });
+    assert_eq!(output, "Say something\nfirst\nYou said first\nsecond\n");
+}
@@ -0,0 +1,247 @@
+use std::cmp::Reverse;
+
+use super::{
+    board_layout::DrawInTerminal,
+    console::outln,
+    theme::{self, Glyphs},
+};
+use crate::{
+    board::layout::Board,
+    engine::{
+        evaluation::MATE,
+        moves::{apply_move, san, Move},
+        Color,
+    },
+};
+
+/// Rows above and below the line for an even game, a pawn each.
+const ROWS: i32 = 5;
+/// The most columns drawn; in longer games moves share a column.
+const WIDTH: usize = 72;
+/// How many of the biggest swings are numbered.
+const SWINGS: usize = 3;
+/// Swings smaller than this many centipawns are not worth a number.
+const SMALLEST_SWING: i32 = 100;
+/// Anything beyond ten pawns counts as ten pawns when looking for swings.
+const CAP: i32 = 1000;
+
+/// The evaluation over the course of a game as a bar chart, rising above
+/// the line while White is better and falling below it while Black is.
+pub struct EvalGraph {
+    /// Each move with its number, e.g. `12... Qxd4`.
+    pub moves: Vec<String>,
+    /// The evaluation after each move in centipawns for White.
+    pub evaluations: Vec<i32>,
+}
+
+/// An evaluation in pawns, e.g. `+1.25`, or `#` for a mate.
+fn pawns(centipawns: i32) -> String {
+    if centipawns.abs() >= MATE - 100 {
+        format!("{}#", if centipawns > 0 { '+' } else { '-' })
+    } else {
+        format!("{:+.2}", centipawns as f64 / 100.0)
+    }
+}
+
+impl EvalGraph {
+    /// Numbers the moves from `first_move`, the fullmove number of the
+    /// position `player` moves first in.
+    pub fn new(
+        board: &Board,
+        player: Color,
+        first_move: usize,
+        moves: &[Move],
+        evaluations: Vec<i32>,
+    ) -> Self {
+        let mut board = board.clone();
+        // Black may have moved first in a game set up from a position.
+        let skipped = usize::from(player == Color::Black);
+        let mut numbered = vec![];
+        for (index, mv) in moves.iter().enumerate() {
+            let ply = index + skipped;
+            let dots = if ply % 2 == 0 { "." } else { "..." };
+            numbered.push(format!(
+                "{}{} {}",
+                first_move + ply / 2,
+                dots,
+                san(&board, mv)
+            ));
+            board = apply_move(board, mv);
+        }
+        EvalGraph {
+            moves: numbered,
+            evaluations,
+        }
+    }
+
+    /// The moves after which the evaluation changed the most, in the order
+    /// they were played.
+    pub fn swings(&self) -> Vec<usize> {
+        let mut swings: Vec<(usize, i32)> = self
+            .evaluations
+            .iter()
+            .enumerate()
+            .map(|(ply, &after)| {
+                let before = if ply == 0 {
+                    0
+                } else {
+                    self.evaluations[ply - 1]
+                };
+                (
+                    ply,
+                    (after.clamp(-CAP, CAP) - before.clamp(-CAP, CAP)).abs(),
+                )
+            })
+            .filter(|(_, swing)| *swing >= SMALLEST_SWING)
+            .collect();
+        swings.sort_by_key(|(ply, swing)| (Reverse(*swing), *ply));
+        let mut plies: Vec<usize> = swings
+            .into_iter()
+            .take(SWINGS)
+            .map(|(ply, _)| ply)
+            .collect();
+        plies.sort();
+        plies
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let count = self.evaluations.len();
+        if count == 0 {
+            return vec!["There are no moves to show.".to_string()];
+        }
+        let (full, rising, falling, level) = match theme::current().glyphs {
+            Glyphs::Unicode => ('█', '▄', '▀', '─'),
+            Glyphs::Ascii => ('#', '.', '\'', '-'),
+        };
+        let columns = count.min(WIDTH);
+        let column_of = |ply: usize| ply * columns / count;
+        // Each column shows the last of its moves, in half pawns.
+        let halves: Vec<i32> = (0..columns)
+            .map(|column| {
+                let ply = (column + 1) * count / columns - 1;
+                let pawns = self.evaluations[ply].clamp(-100 * ROWS, 100 * ROWS) as f64 / 100.0;
+                (pawns * 2.0).round() as i32
+            })
+            .collect();
+        let bar = |row: i32| -> String {
+            halves
+                .iter()
+                .map(|half| {
+                    let reach = half * row.signum();
+                    if reach >= 2 * row.abs() {
+                        full
+                    } else if reach == 2 * row.abs() - 1 {
+                        if row > 0 {
+                            rising
+                        } else {
+                            falling
+                        }
+                    } else {
+                        ' '
+                    }
+                })
+                .collect()
+        };
+        let row =
+            |label: &str, chart: String| format!("{:>3} {}", label, chart).trim_end().to_string();
+
+        let mut lines = vec!["Evaluation for White:".to_string()];
+        for above in (1..=ROWS).rev() {
+            let label = if above == ROWS {
+                format!("{:+}", ROWS)
+            } else {
+                String::new()
+            };
+            lines.push(row(&label, bar(above)));
+        }
+        let swings = self.swings();
+        let mut line = vec![level; columns];
+        for (number, ply) in swings.iter().enumerate() {
+            line[column_of(*ply)] = char::from_digit(number as u32 + 1, 10).unwrap();
+        }
+        lines.push(row("0", line.into_iter().collect()));
+        for below in 1..=ROWS {
+            let label = if below == ROWS {
+                format!("{:+}", -ROWS)
+            } else {
+                String::new()
+            };
+            lines.push(row(&label, bar(-below)));
+        }
+
+        // The move numbers at the first move and every tenth.
+        let mut numbers = String::new();
+        for (ply, text) in self.moves.iter().enumerate() {
+            let number = text.split('.').next().unwrap_or_default();
+            let tick = number
+                .parse::<usize>()
+                .is_ok_and(|number| number == 1 || number.is_multiple_of(10));
+            let column = column_of(ply);
+            if numbers.is_empty() || (tick && column > numbers.len()) {
+                numbers.push_str(&" ".repeat(column - numbers.len()));
+                numbers.push_str(number);
+            }
+        }
+        lines.push(row("", numbers));
+
+        for (number, ply) in swings.iter().enumerate() {
+            let before = if *ply == 0 {
+                0
+            } else {
+                self.evaluations[ply - 1]
+            };
+            lines.push(format!(
+                "{}: {} from {} to {}",
+                number + 1,
+                self.moves[*ply],
+                pawns(before),
+                pawns(self.evaluations[*ply])
+            ));
+        }
+        lines
+    }
+}
+
+impl DrawInTerminal for EvalGraph {
+    fn draw(&self) {
+        for line in self.lines() {
+            outln!("{}", line);
+        }
+    }
+}
+
+#[test]
+fn marks_the_swings() {
+    let graph = EvalGraph {
+        moves: ["1. e3", "1... f6", "2. Qh5+", "2... g6", "3. Qxg6+"]
+            .map(String::from)
+            .to_vec(),
+        evaluations: vec![20, 150, 140, -420, MATE],
+    };
+    assert_eq!(graph.swings(), [1, 3, 4]);
+    let lines = graph.lines();
+    assert_eq!(lines.len(), 1 + 2 * ROWS as usize + 2 + 3);
+    // Mate is cut off at five pawns.
+    assert!(lines[1].ends_with('#') && lines[5] == "     ## #");
+    assert_eq!(lines[6], "  0 -1-23");
+    assert_eq!(lines[10], "       #");
+    assert_eq!(lines[12], "    1");
+    assert_eq!(
+        lines[13..],
+        [
+            "1: 1... f6 from +0.20 to +1.50",
+            "2: 2... g6 from +1.40 to -4.20",
+            "3: 3. Qxg6+ from -4.20 to +#",
+        ]
+    );
+}
+
+#[test]
+fn numbers_moves_from_the_position() {
+    use crate::board::fen::from_fen;
+
+    let (board, player) = from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12").unwrap();
+    let moves = ["e8d8", "e2e3"].map(|mv| Move::from_uci(mv).unwrap());
+    let graph = EvalGraph::new(&board, player, 12, &moves, vec![0, 0]);
+    assert_eq!(graph.moves, ["12... Kd8", "13. e3"]);
+}
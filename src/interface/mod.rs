@@ -1,21 +1,29 @@
 use std::str::FromStr;
 
+use self::console::outln;
 use crate::board::{chesspiece::ChessPieceType, layout::BoardCoordinates};
 
 pub mod board_layout;
+pub mod cli;
+pub mod clock_layout;
+pub mod console;
+pub mod graph_layout;
+pub mod panel_layout;
+pub mod theme;
+pub mod tui;
 
-pub fn get_input<T>(message: &str) -> T
+/// Asks until the answer reads as a `T`, or `None` at the end of the input.
+pub fn get_input<T>(message: &str) -> Option<T>
 where
     T: FromStr,
 {
+    outln!("{}", message);
     loop {
-        println!("{}", message);
-        let mut line = String::new();
-        std::io::stdin().read_line(&mut line).unwrap();
+        let line = console::read_line()?;
         if let Ok(input) = line.parse() {
-            break input;
+            break Some(input);
         }
-        println!("Invalid, try again. {message}");
+        outln!("Invalid, try again. {message}");
     }
 }
 
@@ -41,23 +49,53 @@ pub enum GeneralInput {
     ChessPieceType(ChessPieceType),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum CommandInput {
+    /// Stop the game here.
     Exit,
-    Score, // Not used yet
+    /// Show the material each side has.
+    Score,
     Back,
+    Resign,
+    /// Offer a draw along with the next move.
+    Draw,
+    Chat(String),
+    /// Ask the engine for a move; again for more of it.
+    Hint,
+    /// Save the game to a file, or carry on with one saved earlier.
+    Save(String),
+    Load(String),
 }
 
 impl FromStr for CommandInput {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let command = s.to_ascii_lowercase();
-        if command.starts_with("exit") {
+        // The command in any case, what follows it as it was typed.
+        let argument = |prefix: &str| {
+            let s = s.trim();
+            s.get(..prefix.len())
+                .filter(|word| word.eq_ignore_ascii_case(prefix))
+                .map(|_| s[prefix.len()..].trim().to_owned())
+        };
+        if let Some(text) = argument("chat ") {
+            Ok(Self::Chat(text))
+        } else if let Some(path) = argument("save ") {
+            Ok(Self::Save(path))
+        } else if let Some(path) = argument("load ") {
+            Ok(Self::Load(path))
+        } else if command.starts_with("exit") {
             Ok(Self::Exit)
         } else if command.starts_with("score") {
             Ok(Self::Score)
         } else if command.starts_with("back") {
             Ok(Self::Back)
+        } else if command.starts_with("resign") {
+            Ok(Self::Resign)
+        } else if command.starts_with("draw") {
+            Ok(Self::Draw)
+        } else if command.starts_with("hint") {
+            Ok(Self::Hint)
         } else {
             Err("Could not convert to CommandInput".to_owned())
         }
@@ -75,15 +113,24 @@ fn test_from_strings() {
     let coordinates: BoardCoordinates = FromStr::from_str("A1").unwrap();
     assert_eq!(
         coordinates,
-        BoardCoordinates::from_coordinates(0, 0).unwrap()
+        BoardCoordinates::from_coordinates(7, 0).unwrap()
     );
+    assert_eq!(BoardCoordinates::from_str("a1"), Ok(coordinates));
     let piece_type: ChessPieceType = FromStr::from_str("K").unwrap();
     assert_eq!(piece_type, ChessPieceType::King);
+    assert_eq!(
+        CommandInput::from_str(" Save Games/Mine.json"),
+        Ok(CommandInput::Save("Games/Mine.json".to_string()))
+    );
+    assert_eq!(
+        CommandInput::from_str("CHAT Good Luck"),
+        Ok(CommandInput::Chat("Good Luck".to_string()))
+    );
 }
 
 #[test]
 #[ignore = "Interactive"]
 fn interactive_test() {
-    let command: GeneralInput = get_input("Try a command");
-    println!("{:?}", command);
+    let command: Option<GeneralInput> = get_input("Try a command");
+    outln!("{:?}", command);
 }
@@ -0,0 +1,199 @@
+use termion::{clear, cursor};
+
+use super::{
+    board_layout::{DrawInTerminal, BOARD_TOP},
+    clock_layout::format_duration,
+    console::{self, out, outln},
+    theme,
+};
+use crate::{
+    board::chesspiece::ChessPiece,
+    engine::{
+        evaluation::piece_value,
+        game_state::{check_if_king_in_check, GameState, Players},
+        moves::{apply_move, san},
+        Color,
+    },
+};
+
+/// The column the panel starts in, right of the board.
+const PANEL_COLUMN: u16 = 24;
+/// How many numbered moves fit under the rest of the panel.
+const MOVE_ROWS: usize = 6;
+
+/// Shown next to the board: the clocks, whose turn it is, what each side has
+/// captured and the last moves.
+pub struct Panel<'a> {
+    pub game_state: &'a GameState,
+    pub players: &'a Players,
+}
+
+impl Panel<'_> {
+    pub fn lines(&self) -> Vec<String> {
+        let game_state = self.game_state;
+        let theme = theme::current();
+        let text = theme.fg(theme.text);
+
+        let mut board = game_state.start_board.clone();
+        let mut moves = vec![];
+        let mut captured: Vec<ChessPiece> = vec![];
+        for mv in &game_state.history {
+            moves.push(san(&board, mv));
+            captured.extend(board.at(&mv.to).piece);
+            board = apply_move(board, mv);
+        }
+
+        let mut lines: Vec<String> = [Color::White, Color::Black]
+            .into_iter()
+            .map(|color| match &game_state.clock {
+                Some(clock) => format!(
+                    "{}: {}",
+                    self.players.label(color),
+                    format_duration(clock.remaining(color))
+                ),
+                None => self.players.label(color),
+            })
+            .collect();
+        let player = game_state.current_player;
+        lines.push(match game_state.outcome {
+            Some(outcome) => outcome.describe(|color| self.players.label(color)),
+            None if check_if_king_in_check(&game_state.board, &player) => {
+                format!("{} to move. Check!", player)
+            }
+            None => format!("{} to move.", player),
+        });
+        lines.push(String::new());
+
+        let material = |color: Color| -> i32 {
+            captured
+                .iter()
+                .filter(|piece| piece.player != color)
+                .map(|piece| piece_value(piece.piece_type) / 100)
+                .sum()
+        };
+        let lead = material(Color::White) - material(Color::Black);
+        for color in [Color::White, Color::Black] {
+            let pieces: String = captured
+                .iter()
+                .filter(|piece| piece.player != color)
+                .map(|piece| theme.piece(*piece))
+                .collect();
+            let ahead = match color {
+                Color::White => lead,
+                Color::Black => -lead,
+            };
+            let ahead = if ahead > 0 {
+                format!(" +{}", ahead)
+            } else {
+                String::new()
+            };
+            lines.push(format!("{} took: {}{}{}", color, pieces, text, ahead));
+        }
+        lines.push(String::new());
+
+        // Black may have moved first in a game set up from a position.
+        let skipped = usize::from(game_state.start_player == Color::Black);
+        let mut rows: Vec<String> = vec![];
+        for (index, mv) in moves.iter().enumerate() {
+            let ply = index + skipped;
+            let number = game_state.first_move + ply / 2;
+            match (ply % 2, rows.last_mut()) {
+                (1, Some(row)) => row.push_str(&format!(" {}", mv)),
+                (1, None) => rows.push(format!("{:>3}. ... {}", number, mv)),
+                _ => rows.push(format!("{:>3}. {:<7}", number, mv)),
+            }
+        }
+        lines.push("Moves:".to_string());
+        lines.extend(rows.into_iter().rev().take(MOVE_ROWS).rev());
+        lines
+    }
+}
+
+impl DrawInTerminal for Panel<'_> {
+    /// Next to the board on a terminal, otherwise under it.
+    fn draw(&self) {
+        let theme = theme::current();
+        if !console::is_terminal() {
+            for line in self.lines() {
+                outln!("{}", line);
+            }
+            return;
+        }
+        out!("{}", cursor::Save);
+        for (row, line) in self.lines().into_iter().enumerate() {
+            out!(
+                "{}{}{}{}",
+                cursor::Goto(PANEL_COLUMN, BOARD_TOP + row as u16),
+                theme.fg(theme.text),
+                line,
+                clear::UntilNewline
+            );
+        }
+        out!("{}", cursor::Restore);
+    }
+}
+
+#[test]
+fn lists_moves_and_captures() {
+    use crate::engine::{
+        clock::TimeControl,
+        controller::{HumanController, RandomController},
+        moves::Move,
+    };
+
+    let mut game_state = GameState::start(TimeControl::Unlimited);
+    for mv in ["e2e3", "d7d6", "e3e4", "d6d5", "e4d5", "g8f6"] {
+        game_state = game_state
+            .play(Move::from_uci(mv).unwrap())
+            .unwrap()
+            .switch_player();
+    }
+    let players = Players {
+        white: Box::new(HumanController::new(Some("Alice".to_string()))),
+        black: Box::new(RandomController::seeded(0)),
+    };
+    let lines = Panel {
+        game_state: &game_state,
+        players: &players,
+    }
+    .lines();
+    assert_eq!(lines[0], "Alice (White)");
+    assert_eq!(lines[2], "White to move.");
+    assert!(lines[4].starts_with("White took: ") && lines[4].ends_with(" +1"));
+    assert!(lines[5].starts_with("Black took: ") && !lines[5].contains('+'));
+    assert_eq!(
+        &lines[8..],
+        ["  1. e3      d6", "  2. e4      d5", "  3. exd5    Nf6"]
+    );
+}
+
+#[test]
+fn numbers_moves_from_the_fen() {
+    use crate::{
+        board::fen::{from_fen, fullmove_number},
+        engine::{clock::TimeControl, controller::HumanController, moves::Move},
+    };
+
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 3 41";
+    let (board, player) = from_fen(fen).unwrap();
+    let mut game_state = GameState::from_position(board, player, TimeControl::Unlimited);
+    game_state.first_move = fullmove_number(fen);
+    for mv in ["e8d8", "e2e3"] {
+        game_state = game_state
+            .play(Move::from_uci(mv).unwrap())
+            .unwrap()
+            .switch_player();
+    }
+    let players = Players {
+        white: Box::new(HumanController::new(None)),
+        black: Box::new(HumanController::new(None)),
+    };
+    let lines = Panel {
+        game_state: &game_state,
+        players: &players,
+    }
+    .lines();
+    assert_eq!(lines.last().unwrap(), " 42. e3     ");
+    assert_eq!(lines[lines.len() - 2], " 41. ... Kd8");
+    assert_eq!(game_state.fen(), "3k4/8/8/8/8/4P3/8/4K3 b - - 0 42");
+}
@@ -0,0 +1,306 @@
+use std::{cell::Cell, path::PathBuf, str::FromStr, sync::OnceLock};
+
+use termion::color;
+
+use crate::{board::chesspiece::ChessPiece, engine::Color};
+
+/// A colour in whatever form the terminal takes it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shade {
+    /// The terminal's own foreground or background colour.
+    Default,
+    /// One of the 256 indexed colours.
+    Ansi(u8),
+    /// A 24-bit colour, for terminals that support truecolor.
+    Rgb(u8, u8, u8),
+}
+
+impl Shade {
+    /// A colour from the 6×6×6 cube of the 256 indexed colours.
+    const fn cube(r: u8, g: u8, b: u8) -> Self {
+        Shade::Ansi(16 + 36 * r + 6 * g + b)
+    }
+
+    fn fg(self) -> String {
+        match self {
+            Shade::Default => color::Fg(color::Reset).to_string(),
+            Shade::Ansi(value) => color::Fg(color::AnsiValue(value)).to_string(),
+            Shade::Rgb(r, g, b) => color::Fg(color::Rgb(r, g, b)).to_string(),
+        }
+    }
+
+    fn bg(self) -> String {
+        match self {
+            Shade::Default => color::Bg(color::Reset).to_string(),
+            Shade::Ansi(value) => color::Bg(color::AnsiValue(value)).to_string(),
+            Shade::Rgb(r, g, b) => color::Bg(color::Rgb(r, g, b)).to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Glyphs {
+    /// `K`, `Q`, `R`, `B`, `N` and `P`.
+    Ascii,
+    /// ♔♕♖♗♘♙ for White and ♚♛♜♝♞♟ for Black.
+    Unicode,
+}
+
+impl FromStr for Glyphs {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ascii" | "letters" => Ok(Glyphs::Ascii),
+            "unicode" => Ok(Glyphs::Unicode),
+            other => Err(format!("Unknown glyphs: {} (ascii or unicode)", other)),
+        }
+    }
+}
+
+/// How the board and its pieces are drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub glyphs: Glyphs,
+    /// Without colours nothing but plain text is written, e.g. when the
+    /// output is not a terminal.
+    pub colored: bool,
+    pub text: Shade,
+    pub white: Shade,
+    pub black: Shade,
+    pub light: Shade,
+    pub dark: Shade,
+    pub last_move: Shade,
+    pub check: Shade,
+    pub selected: Shade,
+    pub destination: Shade,
+    pub hint: Shade,
+    pub cursor: Shade,
+}
+
+pub const THEMES: [Theme; 4] = [CLASSIC, OCEAN, WOOD, MONO];
+
+/// Blue and red pieces on brown fields, in 256 colours.
+const CLASSIC: Theme = Theme {
+    name: "classic",
+    glyphs: Glyphs::Ascii,
+    colored: true,
+    text: Shade::Ansi(7),
+    white: Shade::Ansi(4),
+    black: Shade::Ansi(1),
+    light: Shade::cube(5, 4, 3),
+    dark: Shade::cube(3, 2, 1),
+    last_move: Shade::cube(4, 4, 2),
+    check: Shade::cube(5, 1, 1),
+    selected: Shade::cube(1, 4, 1),
+    destination: Shade::cube(2, 4, 5),
+    hint: Shade::cube(4, 2, 5),
+    cursor: Shade::cube(5, 5, 0),
+};
+
+/// White and black pieces on blue fields, in 256 colours.
+const OCEAN: Theme = Theme {
+    name: "ocean",
+    white: Shade::Ansi(231),
+    black: Shade::Ansi(16),
+    light: Shade::cube(2, 4, 5),
+    dark: Shade::cube(1, 2, 4),
+    last_move: Shade::cube(2, 5, 4),
+    selected: Shade::cube(4, 5, 2),
+    destination: Shade::cube(4, 3, 5),
+    ..CLASSIC
+};
+
+/// A wooden board in truecolor.
+const WOOD: Theme = Theme {
+    name: "wood",
+    text: Shade::Rgb(230, 230, 230),
+    white: Shade::Rgb(255, 255, 255),
+    black: Shade::Rgb(0, 0, 0),
+    light: Shade::Rgb(240, 217, 181),
+    dark: Shade::Rgb(181, 136, 99),
+    last_move: Shade::Rgb(205, 210, 106),
+    check: Shade::Rgb(235, 97, 80),
+    selected: Shade::Rgb(130, 151, 105),
+    destination: Shade::Rgb(120, 170, 210),
+    hint: Shade::Rgb(190, 140, 220),
+    cursor: Shade::Rgb(246, 246, 105),
+    ..CLASSIC
+};
+
+/// No colours at all; Black's letters are lower case.
+pub const MONO: Theme = Theme {
+    name: "mono",
+    colored: false,
+    ..CLASSIC
+};
+
+impl Default for Theme {
+    fn default() -> Self {
+        CLASSIC
+    }
+}
+
+impl Theme {
+    pub fn named(name: &str) -> Result<Self, String> {
+        let name = name.trim().to_ascii_lowercase();
+        THEMES
+            .into_iter()
+            .find(|theme| theme.name == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = THEMES.iter().map(|theme| theme.name).collect();
+                format!("Unknown theme: {} ({})", name, names.join(", "))
+            })
+    }
+
+    /// Applies a setting: `theme` with the name of a theme, which keeps the
+    /// glyphs chosen so far, or `glyphs` with `ascii` or `unicode`.
+    pub fn configure(self, key: &str, value: &str) -> Result<Self, String> {
+        match key.trim() {
+            "theme" => Ok(Theme {
+                glyphs: self.glyphs,
+                ..Theme::named(value)?
+            }),
+            "glyphs" => Ok(Theme {
+                glyphs: value.parse()?,
+                ..self
+            }),
+            other => Err(format!("Unknown setting: {}", other)),
+        }
+    }
+
+    /// Applies the `key = value` lines of a config file. Empty lines and
+    /// lines starting with `#` are skipped.
+    pub fn configure_from(self, config: &str) -> Result<Self, String> {
+        config
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .try_fold(self, |theme, line| {
+                let (key, value) = line
+                    .split_once('=')
+                    .ok_or_else(|| format!("Expected `key = value` in the config: {}", line))?;
+                theme.configure(key, value)
+            })
+    }
+
+    pub fn fg(&self, shade: Shade) -> String {
+        if self.colored {
+            shade.fg()
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn bg(&self, shade: Shade) -> String {
+        if self.colored {
+            shade.bg()
+        } else {
+            String::new()
+        }
+    }
+
+    /// The piece in its colour.
+    pub fn piece(&self, piece: ChessPiece) -> String {
+        let shade = match piece.player {
+            Color::White => self.white,
+            Color::Black => self.black,
+        };
+        let glyph = match self.glyphs {
+            Glyphs::Unicode => {
+                let offset = "KQRBNP"
+                    .find(&piece.piece_type.to_string())
+                    .unwrap_or_default() as u32;
+                let first = match piece.player {
+                    Color::White => '♔',
+                    Color::Black => '♚',
+                };
+                char::from_u32(first as u32 + offset)
+                    .unwrap_or(first)
+                    .to_string()
+            }
+            Glyphs::Ascii if !self.colored && piece.player == Color::Black => {
+                piece.piece_type.to_string().to_ascii_lowercase()
+            }
+            Glyphs::Ascii => piece.piece_type.to_string(),
+        };
+        format!("{}{}", self.fg(shade), glyph)
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Picks the theme everything is drawn with. Only the first call counts.
+pub fn set(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+thread_local! {
+    static OVERRIDE: Cell<Option<&'static Theme>> = const { Cell::new(None) };
+}
+
+/// Draws with `theme` on this thread until the returned guard is dropped,
+/// whatever was picked with `set`.
+#[cfg(test)]
+pub fn override_with(theme: &'static Theme) -> Override {
+    Override(OVERRIDE.with(|current| current.replace(Some(theme))))
+}
+
+/// Puts back the theme from before `override_with` when dropped.
+#[cfg(test)]
+pub struct Override(Option<&'static Theme>);
+
+#[cfg(test)]
+impl Drop for Override {
+    fn drop(&mut self) {
+        OVERRIDE.with(|current| current.set(self.0));
+    }
+}
+
+/// The theme this thread draws with: its override, the one picked with
+/// `set`, or the classic one.
+pub fn current() -> &'static Theme {
+    OVERRIDE
+        .with(Cell::get)
+        .unwrap_or_else(|| THEME.get_or_init(Theme::default))
+}
+
+/// `chess/config` in the user's config directory.
+pub fn config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|directory| directory.join("chess").join("config"))
+}
+
+#[test]
+fn themes_and_glyphs() {
+    use crate::board::chesspiece::ChessPieceType;
+
+    let theme = Theme::default()
+        .configure_from("# Mine\ntheme = wood\n\nglyphs = ascii\n")
+        .unwrap();
+    assert_eq!(theme.name, "wood");
+    assert_eq!(theme.glyphs, Glyphs::Ascii);
+    assert!(Theme::default().configure("theme", "plaid").is_err());
+    assert!(Theme::default().configure_from("theme wood").is_err());
+
+    let piece = |piece_type, player| ChessPiece { piece_type, player };
+    let unicode = Theme {
+        glyphs: Glyphs::Unicode,
+        ..MONO
+    };
+    assert_eq!(
+        unicode.piece(piece(ChessPieceType::Knight, Color::White)),
+        "♘"
+    );
+    assert_eq!(
+        unicode.piece(piece(ChessPieceType::Pawn, Color::Black)),
+        "♟"
+    );
+    assert_eq!(MONO.piece(piece(ChessPieceType::Queen, Color::Black)), "q");
+    assert!(CLASSIC
+        .piece(piece(ChessPieceType::Queen, Color::Black))
+        .ends_with('Q'));
+    assert_eq!(MONO.bg(MONO.light), "");
+}
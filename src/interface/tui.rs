@@ -0,0 +1,424 @@
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use termion::{
+    clear, cursor,
+    event::{Event, Key, MouseButton, MouseEvent},
+    input::{MouseTerminal, TermRead},
+    raw::IntoRawMode,
+    screen,
+};
+
+use super::{
+    board_layout::{screen_position, BoardView, Highlights, BOARD_TOP},
+    theme,
+};
+use crate::{
+    board::layout::BoardCoordinates,
+    engine::{
+        clock::Clock,
+        game_state::GameState,
+        moves::{legal_moves, Move},
+        Color,
+    },
+};
+
+/// Under the board and the up to four lines `game_engine` prints after it.
+const STATUS_ROW: u16 = BOARD_TOP + BoardView::HEIGHT + 4;
+/// Where the clocks tick while the player picks a move, under the title,
+/// the keys and the note.
+pub const CLOCK_ROW: u16 = STATUS_ROW + 3;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Switches to moving the pieces with the cursor, as long as both input and
+/// output are a terminal. Returns whether it did.
+pub fn enable() -> bool {
+    let tty = termion::is_tty(&io::stdin()) && termion::is_tty(&io::stdout());
+    ENABLED.store(tty, Ordering::Relaxed);
+    tty
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Clears the terminal and moves the cursor to the top left corner. Output
+/// that is not a terminal is left alone.
+pub fn redraw() {
+    if termion::is_tty(&io::stdout()) {
+        print!("{}{}", clear::All, cursor::Goto(1, 1));
+    }
+}
+
+/// Shows the game on the alternate screen until dropped, so the terminal
+/// looks as it did before once the game is over.
+pub struct Screen;
+
+impl Screen {
+    pub fn enter() -> Self {
+        print!("{}", screen::ToAlternateScreen);
+        io::stdout().flush().unwrap();
+        Screen
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        print!("{}{}", cursor::Show, screen::ToMainScreen);
+        io::stdout().flush().unwrap();
+    }
+}
+
+/// What the player did on the board.
+pub enum Action {
+    Move(Move),
+    Resign,
+    OfferDraw,
+    Chat(String),
+    /// Asks for a hint, or more of it.
+    Hint,
+    /// Saves the game to a file, or loads one instead.
+    Save(String),
+    Load(String),
+    /// The player's time ran out while picking.
+    OutOfTime,
+}
+
+/// What `choose` shows besides the board and the keys.
+#[derive(Default)]
+pub struct Prompt<'a> {
+    pub title: &'a str,
+    /// A line under the keys, e.g. about a draw offer or a hint.
+    pub note: String,
+    /// Fields the hint points at.
+    pub hint: Vec<BoardCoordinates>,
+    pub can_chat: bool,
+}
+
+/// Lets the player pick a piece and its destination with the arrow keys
+/// (or `hjkl`) and Enter, or by clicking. `at` is where the cursor starts
+/// and is left where it ended. The board must already be on the screen with
+/// `bottom` at the bottom.
+pub fn choose(
+    game_state: &GameState,
+    bottom: Color,
+    prompt: &Prompt,
+    at: &mut Option<BoardCoordinates>,
+) -> Action {
+    print!("{}", cursor::Hide);
+    let action = pick(game_state, bottom, prompt, at);
+    print!("{}{}", cursor::Show, cursor::Goto(1, CLOCK_ROW + 1));
+    io::stdout().flush().unwrap();
+    action
+}
+
+/// Where the cursor is on the board as drawn and which piece it picked up.
+struct Selection<'a> {
+    game_state: &'a GameState,
+    legal: Vec<Move>,
+    bottom: Color,
+    row: u16,
+    column: u16,
+    selected: Option<BoardCoordinates>,
+}
+
+/// What pressing Enter on a field comes to.
+#[derive(Debug, PartialEq)]
+enum Press {
+    Picked,
+    Move(Move),
+    /// A pawn reaches the last rank; which piece it becomes is still open.
+    Promotion(Vec<Move>),
+    Refused(&'static str),
+}
+
+impl<'a> Selection<'a> {
+    /// Starts at `at`, or else at the first piece that can move.
+    fn new(game_state: &'a GameState, bottom: Color, at: Option<BoardCoordinates>) -> Self {
+        let legal = legal_moves(&game_state.board, &game_state.current_player);
+        let start = at.or(legal.first().map(|mv| mv.from));
+        let (row, column) = start.map_or((7, 4), |field| screen_position(field, bottom));
+        Selection {
+            game_state,
+            legal,
+            bottom,
+            row,
+            column,
+            selected: None,
+        }
+    }
+
+    fn field(&self) -> BoardCoordinates {
+        BoardCoordinates::from_coordinates(self.row.into(), self.column.into())
+            .unwrap()
+            .oriented(self.bottom)
+    }
+
+    /// Moves the cursor for an arrow key or one of `hjkl`, or drops the
+    /// piece for Esc. Whether the key was one of these.
+    fn steer(&mut self, key: Key) -> bool {
+        match key {
+            Key::Up | Key::Char('k') => self.row = self.row.saturating_sub(1),
+            Key::Down | Key::Char('j') => self.row = (self.row + 1).min(7),
+            Key::Left | Key::Char('h') => self.column = self.column.saturating_sub(1),
+            Key::Right | Key::Char('l') => self.column = (self.column + 1).min(7),
+            Key::Esc | Key::Backspace => self.selected = None,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Picks up the piece under the cursor, or puts the one picked up there.
+    fn press(&mut self) -> Press {
+        let field = self.field();
+        let player = self.game_state.current_player;
+        let own = self.game_state.board.at(&field).check_player() == Some(player);
+        let candidates: Vec<Move> = self
+            .legal
+            .iter()
+            .filter(|mv| Some(mv.from) == self.selected && mv.to == field)
+            .copied()
+            .collect();
+        match candidates.as_slice() {
+            [] if own && self.legal.iter().any(|mv| mv.from == field) => {
+                self.selected = Some(field);
+                Press::Picked
+            }
+            [] if own => Press::Refused("This figure has no possible moves!"),
+            [] if self.selected.is_some() => Press::Refused("That is not a legal move."),
+            [] => Press::Refused("This field doesn't have your figure."),
+            [mv] => Press::Move(*mv),
+            promotions => Press::Promotion(promotions.to_vec()),
+        }
+    }
+
+    fn highlights(&self, prompt: &Prompt) -> Highlights {
+        Highlights {
+            cursor: Some(self.field()),
+            hint: prompt.hint.clone(),
+            ..Highlights::of(self.game_state)
+        }
+        .selecting(self.selected, &self.legal)
+    }
+}
+
+fn pick(
+    game_state: &GameState,
+    bottom: Color,
+    prompt: &Prompt,
+    at: &mut Option<BoardCoordinates>,
+) -> Action {
+    let mut selection = Selection::new(game_state, bottom, *at);
+    let raw = io::stdout().into_raw_mode().unwrap();
+    let mouse = MouseTerminal::from(io::stdout());
+    let mut events = io::stdin().events();
+    let mut message = prompt.note.clone();
+    let help = format!(
+        "Arrows/hjkl move, Enter selects, Esc cancels, ? hint, d draw, r resign, s save, o load{}",
+        if prompt.can_chat { ", c chat" } else { "" }
+    );
+    loop {
+        *at = Some(selection.field());
+        // The clock ticker waits until the frame is drawn.
+        let frame = io::stdout().lock();
+        paint(&BoardView {
+            board: &game_state.board,
+            bottom,
+            highlights: selection.highlights(prompt),
+        });
+        print!(
+            "{}{}{}{}\r\n{}{}\r\n{}{}",
+            cursor::Goto(1, STATUS_ROW),
+            clear::CurrentLine,
+            theme::current().fg(theme::current().text),
+            prompt.title,
+            clear::CurrentLine,
+            help,
+            clear::CurrentLine,
+            message
+        );
+        io::stdout().flush().unwrap();
+        drop(frame);
+        message.clear();
+
+        let Some(Ok(event)) = events.next() else {
+            return Action::Resign;
+        };
+        if game_state.clock.as_ref().and_then(Clock::out_of_time) == Some(game_state.current_player)
+        {
+            return Action::OutOfTime;
+        }
+        match event {
+            Event::Key(Key::Char('\n' | ' ')) => {}
+            Event::Key(key) if selection.steer(key) => continue,
+            Event::Key(Key::Char('d')) => return Action::OfferDraw,
+            Event::Key(Key::Char('?')) => return Action::Hint,
+            Event::Key(Key::Char('r')) => {
+                if ask("Resign? (y/n)", &mut events) == Some('y') {
+                    return Action::Resign;
+                }
+                continue;
+            }
+            Event::Key(Key::Char('c')) if prompt.can_chat => {
+                if let Some(text) = read_line("Say: ", &mut events) {
+                    return Action::Chat(text);
+                }
+                continue;
+            }
+            Event::Key(Key::Char('s')) => {
+                if let Some(path) = read_line("Save to: ", &mut events) {
+                    return Action::Save(path);
+                }
+                continue;
+            }
+            Event::Key(Key::Char('o')) => {
+                if let Some(path) = read_line("Load from: ", &mut events) {
+                    return Action::Load(path);
+                }
+                continue;
+            }
+            Event::Key(Key::Ctrl('c')) => {
+                drop(mouse);
+                drop(raw);
+                print!("{}{}", cursor::Show, screen::ToMainScreen);
+                io::stdout().flush().unwrap();
+                std::process::exit(130);
+            }
+            Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) => {
+                match board_position(x, y) {
+                    Some(position) => (selection.row, selection.column) = position,
+                    None => continue,
+                }
+            }
+            _ => continue,
+        }
+
+        match selection.press() {
+            Press::Picked => {}
+            Press::Move(mv) => return Action::Move(mv),
+            Press::Promotion(promotions) => {
+                let prompt = "Promote to (q)ueen, (r)ook, (b)ishop or k(n)ight";
+                let promotion = ask(prompt, &mut events)
+                    .and_then(|key| key.to_ascii_uppercase().to_string().parse().ok());
+                match promotions.iter().find(|mv| mv.promotion == promotion) {
+                    Some(mv) => return Action::Move(*mv),
+                    None => message = "The pawn can't be replaced with that piece.".to_string(),
+                }
+            }
+            Press::Refused(reason) => message = reason.to_string(),
+        }
+    }
+}
+
+/// The board row and column under the terminal cell at `x`, `y`.
+fn board_position(x: u16, y: u16) -> Option<(u16, u16)> {
+    let row = y.checked_sub(BOARD_TOP + 1)?;
+    let column = x.checked_sub(3)? / 2;
+    (row < 8 && column < 8).then_some((row, column))
+}
+
+/// Draws the fields again, e.g. with the cursor, the selected piece and
+/// where it can go highlighted.
+fn paint(view: &BoardView) {
+    for index in 0..64 {
+        let field = BoardCoordinates::from_index(index).unwrap();
+        let (row, column) = screen_position(field, view.bottom);
+        print!("{}", cursor::Goto(3 + 2 * column, BOARD_TOP + 1 + row));
+        view.draw_field(field);
+    }
+}
+
+/// Shows `prompt` on the status line and waits for a single key.
+fn ask(prompt: &str, events: &mut impl Iterator<Item = io::Result<Event>>) -> Option<char> {
+    print!(
+        "{}{}{}",
+        cursor::Goto(1, STATUS_ROW + 2),
+        clear::CurrentLine,
+        prompt
+    );
+    io::stdout().flush().unwrap();
+    match events.next()? {
+        Ok(Event::Key(Key::Char(key))) => Some(key.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+/// Reads a line on the status line. `None` if Esc cancels it.
+fn read_line(prompt: &str, events: &mut impl Iterator<Item = io::Result<Event>>) -> Option<String> {
+    let mut text = String::new();
+    loop {
+        print!(
+            "{}{}{}{}{}",
+            cursor::Goto(1, STATUS_ROW + 2),
+            clear::CurrentLine,
+            prompt,
+            text,
+            cursor::Show
+        );
+        io::stdout().flush().unwrap();
+        match events.next()? {
+            Ok(Event::Key(Key::Char('\n'))) => break,
+            Ok(Event::Key(Key::Char(c))) => text.push(c),
+            Ok(Event::Key(Key::Backspace)) => {
+                text.pop();
+            }
+            Ok(Event::Key(Key::Esc)) => return None,
+            _ => {}
+        }
+    }
+    print!("{}", cursor::Hide);
+    (!text.trim().is_empty()).then(|| text.trim().to_string())
+}
+
+#[test]
+fn maps_clicks_to_fields() {
+    assert_eq!(board_position(3, BOARD_TOP + 1), Some((0, 0)));
+    assert_eq!(board_position(4, BOARD_TOP + 1), Some((0, 0)));
+    assert_eq!(board_position(17, BOARD_TOP + 8), Some((7, 7)));
+    assert_eq!(board_position(19, BOARD_TOP + 8), None);
+    assert_eq!(board_position(1, BOARD_TOP + 4), None);
+    assert_eq!(board_position(5, BOARD_TOP), None);
+}
+
+#[test]
+fn keys_pick_a_piece_and_its_move() {
+    use crate::engine::clock::TimeControl;
+
+    let game_state = GameState::start(TimeControl::Unlimited);
+    let e2 = "e2".parse().unwrap();
+    let mut selection = Selection::new(&game_state, Color::White, Some(e2));
+    assert_eq!(selection.press(), Press::Picked);
+    assert_eq!(selection.highlights(&Prompt::default()).selected, Some(e2));
+    assert!(selection.steer(Key::Char('k')));
+    assert_eq!(
+        selection.press(),
+        Press::Move(Move::from_uci("e2e3").unwrap())
+    );
+    assert!(selection.steer(Key::Left));
+    assert_eq!(
+        selection.press(),
+        Press::Refused("That is not a legal move.")
+    );
+    assert!(selection.steer(Key::Esc));
+    assert_eq!(
+        selection.press(),
+        Press::Refused("This field doesn't have your figure.")
+    );
+    assert!(!selection.steer(Key::Char('x')));
+
+    // With Black at the bottom, up is towards White's side.
+    let game_state = game_state
+        .play(Move::from_uci("e2e3").unwrap())
+        .unwrap()
+        .switch_player();
+    let mut selection = Selection::new(&game_state, Color::Black, Some("g8".parse().unwrap()));
+    assert_eq!(selection.press(), Press::Picked);
+    assert!(selection.steer(Key::Up) && selection.steer(Key::Up) && selection.steer(Key::Left));
+    assert_eq!(
+        selection.press(),
+        Press::Move(Move::from_uci("g8h6").unwrap())
+    );
+}
@@ -5,31 +5,858 @@ mod board;
 mod common;
 mod engine;
 mod interface;
-use std::io::{self, Write};
+use std::{
+    io::{self, Write},
+    net::TcpListener,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use engine::game_state::GameState;
-use interface::board_layout::DrawInTerminal;
+use board::fen::{from_fen, fullmove_number, STARTING_FEN};
+
+use engine::{
+    analysis::{analyze, Analysis},
+    clock::{Clock, TimeControl},
+    controller::{
+        EngineController, HumanController, PlayerController, RandomController, Request,
+        ScriptedController,
+    },
+    game_state::{GameState, Players},
+    moves::{apply_move, legal_moves, perft},
+    pgn::{eval_in, read_pgn, to_pgn, PgnGame},
+    remote::{Connection, RemoteController},
+    save::SavedGame,
+    search::{Engine, SearchOptions, BENCH_POSITIONS},
+    selfplay::{parse_openings, run_match, Contestant, MatchOptions, OPENINGS},
+    server::{self, Server},
+    skill::Skill,
+    sprt::{run_sprt, SprtOptions},
+    transposition::splitmix64,
+    uci::{self, UciController, UciEngine},
+    xboard, Color,
+};
+use interface::{
+    board_layout::{BoardView, DrawInTerminal, Highlights},
+    cli::{self, Command},
+    console::{self, out, outln},
+    get_input,
+    graph_layout::EvalGraph,
+    panel_layout::Panel,
+    theme::{self, Theme},
+    tui,
+};
 
 fn main() {
-    game_engine()
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cli = cli::parse(&args).unwrap_or_else(|error| {
+        eprintln!("{}\nRun chess --help for the options.", error);
+        std::process::exit(2);
+    });
+    if cli.help {
+        return print!("{}", cli::usage(cli.command));
+    }
+    let args = cli.args;
+    let result = match cli.command {
+        Command::Play => play(&args),
+        Command::Analyze => analyze_from_args(&args),
+        Command::Review => review_from_args(&args),
+        Command::Perft => perft_from_args(&args),
+        Command::Bench => bench_from_args(&args),
+        Command::Match => match_from_args(&args),
+        Command::Sprt => sprt_from_args(&args),
+        Command::Serve => serve_from_args(&args),
+        Command::Uci => {
+            uci::serve(io::stdin().lock(), io::stdout());
+            Ok(())
+        }
+        Command::Xboard => {
+            xboard::serve(io::stdin().lock(), io::stdout());
+            Ok(())
+        }
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        std::process::exit(2);
+    }
+}
+
+/// A game at the terminal, from `--fen`, after the moves of the game in
+/// `--pgn` or from the standard position, and with the clock set by `--time`
+/// or asked for. `--save-pgn` saves the game at the end. The random choices
+/// follow from `--seed`, or from a seed picked at random and printed, so
+/// that a game can be played again the same way.
+/// `--script <file>` reads what would be typed from the file, one line each.
+fn play(args: &[String]) -> Result<(), String> {
+    theme::set(theme_from_args(args)?);
+    let _script = match arg_value(args, "--script") {
+        Some(path) => {
+            let file = std::fs::File::open(path)
+                .map_err(|error| format!("Could not read {}: {}", path, error))?;
+            Some(console::redirect(io::BufReader::new(file), io::stdout()))
+        }
+        None => None,
+    };
+    let time_control = parsed::<TimeControl>(args, "--time")?;
+    let start = match (arg_value(args, "--fen"), arg_value(args, "--pgn")) {
+        (Some(_), Some(_)) => return Err("Give either --fen or --pgn, not both.".to_string()),
+        (Some(fen), None) => {
+            let (board, player) = from_fen(fen)?;
+            Some(GameState {
+                first_move: fullmove_number(fen),
+                ..GameState::from_position(board, player, TimeControl::Unlimited)
+            })
+        }
+        (None, Some(path)) => Some(game_from_pgn(path)?.1),
+        (None, None) => None,
+    };
+    let new_game = |time_control| match &start {
+        Some(start) => GameState {
+            clock: Clock::new(time_control),
+            ..start.clone()
+        },
+        None => GameState::start(time_control),
+    };
+    let network = args.iter().any(|arg| arg == "--host" || arg == "--connect");
+    let seed = parsed::<u64>(args, "--seed")?.unwrap_or_else(rand::random);
+    let (players, mut game_state) = if let Some(path) = arg_value(args, "--resume") {
+        resume(args, path)?
+    } else if network {
+        if start.is_some() {
+            return Err("Games over the network start from the standard position.".to_string());
+        }
+        let (players, time_control) = network_players_from_args(args, time_control)?;
+        (players, new_game(time_control))
+    } else {
+        let players = players_from_args(args, seed)?;
+        let time_control = match time_control {
+            Some(time_control) => time_control,
+            None => ask(TIME_CONTROL_PROMPT)?,
+        };
+        (players, new_game(time_control))
+    };
+    let seed = *game_state.seed.get_or_insert(seed);
+    outln!("Seed: {}", seed);
+    let flip = args.iter().any(|arg| arg == "--flip");
+    if !args.iter().any(|arg| arg == "--line" || arg == "--script") {
+        tui::enable();
+    }
+    let broadcast = broadcast_from_args(args)?;
+    let load = |path: &str| resume(args, path);
+    let (game_state, players) = game_engine(players, game_state, flip, broadcast, load);
+    if args.iter().any(|arg| arg == "--analyze") {
+        analyze_game(args, &game_state, &players)?;
+    } else if let Some(path) = arg_value(args, "--save-pgn") {
+        let result = game_state.outcome.map_or("*", |outcome| outcome.result());
+        let pgn = to_pgn(
+            &game_tags(&game_state, &players, result),
+            &game_state.history,
+            result,
+        );
+        std::fs::write(path, pgn)
+            .map_err(|error| format!("Could not write {}: {}", path, error))?;
+    }
+    Ok(())
 }
 
-fn game_engine() {
-    let mut game_state = GameState::start();
-    loop {
-        let _ = std::process::Command::new("clear").status();
-        game_state.board.draw();
+const TIME_CONTROL_PROMPT: &str =
+    "Choose a time control, e.g. 5+3, 15d5, 10b5 or 40/90+30:30+30 (empty for none)";
+
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|position| args.get(position + 1))
+}
+
+/// Asks for something needed before the game can start.
+fn ask<T: FromStr>(message: &str) -> Result<T, String> {
+    get_input(message).ok_or_else(|| "The input ended before the game started.".to_string())
+}
+
+/// The value of option `name`, if given, read as a `T`.
+fn parsed<T: FromStr>(args: &[String], name: &str) -> Result<Option<T>, String> {
+    arg_value(args, name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid {}: {}", name, value))
+        })
+        .transpose()
+}
+
+/// `--mode hotseat` (or `--hotseat`) lets two people share the terminal and
+/// `--mode engine` has the engine play itself. `--white` and `--black` pick
+/// who plays each side: `human`, `random`, `engine`, `script:<file>` with the
+/// moves to play or `uci:<command>` to start an external engine. Otherwise
+/// you play the colour from `--color` against the computer, which is the
+/// engine started by `--uci-engine`, this program's engine if `--engine` (or
+/// any of the engine options below) is given, or else moves at random.
+/// External engines search with the limits in `--go` when there is no clock.
+fn players_from_args(args: &[String], seed: u64) -> Result<Players, String> {
+    let mode = arg_value(args, "--mode").map_or("human", String::as_str);
+    if !["human", "engine", "hotseat"].contains(&mode) {
+        return Err(format!("Unknown mode: {} (human, engine or hotseat)", mode));
+    }
+    if mode == "hotseat" || args.iter().any(|arg| arg == "--hotseat") {
+        let name = |color: Color| -> Result<HumanController, String> {
+            let name: String = ask(&format!("Name of the {} player (empty for none)", color))?;
+            let name = name.trim();
+            Ok(HumanController::new(
+                (!name.is_empty()).then(|| name.to_string()),
+            ))
+        };
+        return Ok(Players {
+            white: Box::new(name(Color::White)?),
+            black: Box::new(name(Color::Black)?),
+        });
+    }
+    // The level says what is wrong with it, e.g. that it is out of range.
+    let mut skill = match arg_value(args, "--skill") {
+        Some(level) => Some(
+            level
+                .parse::<Skill>()
+                .map_err(|error| format!("Invalid --skill: {}", error))?,
+        ),
+        None => parsed::<u32>(args, "--elo")?.map(Skill::from_elo),
+    };
+    let threads = parsed::<usize>(args, "--threads")?;
+    let depth = parsed::<u32>(args, "--depth")?;
+    let wants_engine = skill.is_some()
+        || threads.is_some()
+        || depth.is_some()
+        || args.iter().any(|arg| arg == "--engine");
+    let default = SearchOptions::default();
+    let search_options = SearchOptions {
+        threads: threads.unwrap_or(default.threads),
+        depth: depth.unwrap_or(default.depth),
+        ..default
+    };
+
+    let uci_opponent = arg_value(args, "--uci-engine").map(|command| format!("uci:{}", command));
+    let go = arg_value(args, "--go").map_or("movetime 1000", String::as_str);
+
+    let (white, black) = match (arg_value(args, "--white"), arg_value(args, "--black")) {
+        (None, None) if mode == "engine" => ("engine", "engine"),
+        (None, None) => {
+            let color = match arg_value(args, "--color") {
+                Some(color) => color.parse::<Color>()?,
+                None => ask("Choose your colour: white or black (empty for white)")?,
+            };
+            let opponent = match &uci_opponent {
+                Some(opponent) => opponent.as_str(),
+                None if wants_engine => "engine",
+                None => "random",
+            };
+            match color {
+                Color::White => ("human", opponent),
+                Color::Black => (opponent, "human"),
+            }
+        }
+        (white, black) => (
+            white.map_or("human", String::as_str),
+            black.map_or("human", String::as_str),
+        ),
+    };
+    Ok(Players {
+        white: controller_from_arg(white, search_options, &mut skill, go, seed)?,
+        black: controller_from_arg(black, search_options, &mut skill, go, splitmix64(seed))?,
+    })
+}
+
+/// `--resume <file>` carries on with a game saved with `save <file>`, with
+/// the same players, the clocks as they were and the saved seed unless
+/// `--seed` gives another. Random players go on as if there had been no save.
+fn resume(args: &[String], path: &str) -> Result<(Players, GameState), String> {
+    let mut saved = SavedGame::load(path)?;
+    let seed = match parsed::<u64>(args, "--seed")? {
+        Some(seed) => seed,
+        None => saved.game_state.seed.unwrap_or_else(rand::random),
+    };
+    saved.game_state.seed = Some(seed);
+    let go = arg_value(args, "--go").map_or("movetime 1000", String::as_str);
+    let controller = |setup: &str, seed| {
+        controller_from_arg(setup, SearchOptions::default(), &mut None, go, seed)
+    };
+    let mut players = Players {
+        white: controller(&saved.white, seed)?,
+        black: controller(&saved.black, splitmix64(seed))?,
+    };
+    for color in [Color::White, Color::Black] {
+        players.get_mut(color).resumed(&saved.game_state, color);
+    }
+    Ok((players, saved.game_state))
+}
+
+/// `--host <address>` waits for another instance of this program to
+/// `--connect <address>`, and the two play each other over the network. The
+/// host picks its colour (`--color`) and the time control (`--time`, or
+/// asked for). `--name` is shown to the other side.
+fn network_players_from_args(
+    args: &[String],
+    time_control: Option<TimeControl>,
+) -> Result<(Players, TimeControl), String> {
+    let name = arg_value(args, "--name").cloned();
+    let (connection, color, time_control) =
+        match (arg_value(args, "--host"), arg_value(args, "--connect")) {
+            (Some(address), _) => {
+                let color = match arg_value(args, "--color") {
+                    Some(color) => color.parse::<Color>()?,
+                    None => ask("Choose your colour: white or black (empty for white)")?,
+                };
+                let time_control = match time_control {
+                    Some(time_control) => time_control,
+                    None => ask(TIME_CONTROL_PROMPT)?,
+                };
+                let listener = TcpListener::bind(address)
+                    .map_err(|error| format!("Could not listen on {}: {}", address, error))?;
+                if let Ok(address) = listener.local_addr() {
+                    outln!("Waiting for an opponent on {}...", address);
+                }
+                let connection =
+                    Connection::host(listener, name.clone(), color.switch(), &time_control)?;
+                (connection, color, time_control)
+            }
+            (None, Some(address)) => Connection::connect(address, name.clone())?,
+            (None, None) => return Err("--host and --connect need an address".to_string()),
+        };
+    outln!(
+        "Playing {} as {} with time control {}",
+        connection
+            .peer_name
+            .as_deref()
+            .unwrap_or("an unnamed opponent"),
+        color,
+        time_control
+    );
+    let mut human = Box::new(HumanController::new(name));
+    human.chat = Some(connection.chat());
+    let remote = Box::new(RemoteController::new(connection, color.switch()));
+    let players = match color {
+        Color::White => Players {
+            white: human,
+            black: remote,
+        },
+        Color::Black => Players {
+            white: remote,
+            black: human,
+        },
+    };
+    Ok((players, time_control))
+}
+
+/// `--match <games>` pits `--first` against `--second` (see `Contestant`)
+/// without drawing the board, starting from the openings in `--openings`
+/// and writing the games to `--save-pgn`. Games are drawn after `--max-moves`.
+/// The random mover's moves follow from `--seed`.
+fn match_from_args(args: &[String]) -> Result<(), String> {
+    let contestant = |name, default: &str| {
+        arg_value(args, name)
+            .map_or(default, String::as_str)
+            .parse::<Contestant>()
+    };
+    let (first, second) = (
+        contestant("--first", "engine")?,
+        contestant("--second", "random")?,
+    );
+    let openings = match arg_value(args, "--openings") {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|error| format!("Could not read {}: {}", path, error))?,
+        None => OPENINGS.to_string(),
+    };
+    let options = MatchOptions {
+        games: parsed(args, "--match")?.ok_or("--match needs the number of games")?,
+        openings: parse_openings(&openings)?,
+        max_plies: 2 * parsed::<usize>(args, "--max-moves")?.unwrap_or(100),
+        seed: parsed::<u64>(args, "--seed")?.unwrap_or_else(rand::random),
+    };
+    println!("Seed: {}", options.seed);
+    let mut pgn = arg_value(args, "--save-pgn")
+        .map(|path| {
+            std::fs::File::create(path)
+                .map_err(|error| format!("Could not create {}: {}", path, error))
+        })
+        .transpose()?;
+    let score = run_match(
+        &first,
+        &second,
+        &options,
+        pgn.as_mut().map(|file| file as &mut dyn Write),
+    )?;
+    println!("Final: {} vs {}: {}", first.name, second.name, score);
+    Ok(())
+}
+
+/// `--sprt` runs a sequential test between the UCI engines started by the
+/// commands `--first` and `--second` (this program in UCI mode by default),
+/// with `--elo0`, `--elo1`, `--alpha`, `--beta`, `--games` as the most games
+/// to play, `--concurrency`, `--go` limits for every move, `--openings` and
+/// `--max-moves`.
+fn sprt_from_args(args: &[String]) -> Result<(), String> {
+    let this_engine = std::env::current_exe()
+        .map(|path| format!("{} --uci", path.display()))
+        .map_err(|error| format!("Could not find this program: {}", error))?;
+    let command = |name| {
+        arg_value(args, name)
+            .cloned()
+            .unwrap_or(this_engine.clone())
+    };
+    let openings = match arg_value(args, "--openings") {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|error| format!("Could not read {}: {}", path, error))?,
+        None => OPENINGS.to_string(),
+    };
+    let options = SprtOptions {
+        elo0: parsed(args, "--elo0")?.unwrap_or(0.0),
+        elo1: parsed(args, "--elo1")?.unwrap_or(5.0),
+        alpha: parsed(args, "--alpha")?.unwrap_or(0.05),
+        beta: parsed(args, "--beta")?.unwrap_or(0.05),
+        max_games: parsed(args, "--games")?.unwrap_or(20_000),
+        concurrency: parsed(args, "--concurrency")?.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, |threads| threads.get())
+        }),
+        go: arg_value(args, "--go")
+            .cloned()
+            .unwrap_or("depth 4".to_string()),
+        openings: parse_openings(&openings)?,
+        max_plies: 2 * parsed(args, "--max-moves")?.unwrap_or(100),
+    };
+    let summary = run_sprt(&command("--first"), &command("--second"), &options)?;
+    println!("{}", summary);
+    Ok(())
+}
+
+/// `--serve <address>` runs the JSON game server (see `Server::handle`),
+/// saving its games to `--store <directory>` if given.
+fn serve_from_args(args: &[String]) -> Result<(), String> {
+    let address = arg_value(args, "--serve").ok_or("--serve needs an address")?;
+    let server = Server::new(arg_value(args, "--store").map(Into::into))?;
+    let listener = TcpListener::bind(address)
+        .map_err(|error| format!("Could not listen on {}: {}", address, error))?;
+    if let Ok(address) = listener.local_addr() {
+        println!("Serving games on http://{}", address);
+    }
+    server::serve(listener, Arc::new(server));
+    Ok(())
+}
+
+/// `--broadcast <address>` serves the game while it is played, so that a
+/// browser or anyone else can follow it over a WebSocket.
+fn broadcast_from_args(args: &[String]) -> Result<Option<Arc<Server>>, String> {
+    let Some(address) = arg_value(args, "--broadcast") else {
+        return Ok(None);
+    };
+    let server = Arc::new(Server::new(None)?);
+    let listener = TcpListener::bind(address)
+        .map_err(|error| format!("Could not listen on {}: {}", address, error))?;
+    if let Ok(address) = listener.local_addr() {
+        outln!("Follow the game at ws://{}/games/1/events", address);
+    }
+    let serving = Arc::clone(&server);
+    std::thread::spawn(move || server::serve(listener, serving));
+    Ok(Some(server))
+}
+
+/// The theme from the config file (`--config <file>`, or else the one in
+/// the user's config directory if there is one), then `--theme` and
+/// `--glyphs`. Without a terminal to draw on there are no colours.
+fn theme_from_args(args: &[String]) -> Result<Theme, String> {
+    let mut theme = Theme::default();
+    let config = match arg_value(args, "--config") {
+        Some(path) => Some(
+            std::fs::read_to_string(path)
+                .map_err(|error| format!("Could not read {}: {}", path, error))?,
+        ),
+        None => theme::config_path().and_then(|path| std::fs::read_to_string(path).ok()),
+    };
+    if let Some(config) = config {
+        theme = theme.configure_from(&config)?;
+    }
+    for (flag, key) in [("--theme", "theme"), ("--glyphs", "glyphs")] {
+        if let Some(value) = arg_value(args, flag) {
+            theme = theme.configure(key, value)?;
+        }
+    }
+    theme.colored &= termion::is_tty(&io::stdout());
+    Ok(theme)
+}
+
+fn controller_from_arg(
+    name: &str,
+    search_options: SearchOptions,
+    skill: &mut Option<Skill>,
+    go: &str,
+    seed: u64,
+) -> Result<Box<dyn PlayerController>, String> {
+    match name {
+        "human" => Ok(Box::new(HumanController::default())),
+        "random" => Ok(Box::new(RandomController::seeded(seed))),
+        other if other.starts_with("engine:") => Ok(other.parse::<Contestant>()?.controller(seed)),
+        "engine" => {
+            let skill = match *skill {
+                Some(skill) => skill,
+                None => *skill.insert(ask(
+                    "Choose a level from 1 to 20 or an Elo like 1500elo (empty for full strength)",
+                )?),
+            };
+            outln!("The engine plays at {}", skill);
+            Ok(Box::new(EngineController::new(Engine::new(
+                SearchOptions {
+                    skill,
+                    seed,
+                    ..search_options
+                },
+            ))))
+        }
+        other => {
+            if let Some(name) = other.strip_prefix("human:") {
+                Ok(Box::new(HumanController::new(Some(name.to_string()))))
+            } else if let Some(path) = other.strip_prefix("script:") {
+                std::fs::read_to_string(path)
+                    .map_err(|error| format!("Could not read {}: {}", path, error))?
+                    .parse::<ScriptedController>()
+                    .map(|controller| Box::new(controller) as Box<dyn PlayerController>)
+            } else if let Some(command) = other.strip_prefix("uci:") {
+                let engine = UciEngine::spawn(command)?;
+                outln!("Playing against {}", engine.name);
+                Ok(Box::new(UciController::new(engine, go)))
+            } else {
+                Err(format!("Unknown player: {}", other))
+            }
+        }
+    }
+}
 
+/// Plays `game_state` to the end. With `flip`, the board turns between moves
+/// so that whoever is to move at the terminal sees their own pieces at the
+/// bottom. With `broadcast`, every move is passed on to the server for those
+/// following the game. `load` sets up a saved game when a player asks for
+/// one.
+fn game_engine(
+    mut players: Players,
+    mut game_state: GameState,
+    flip: bool,
+    broadcast: Option<Arc<Server>>,
+    load: impl Fn(&str) -> Result<(Players, GameState), String>,
+) -> (GameState, Players) {
+    let broadcast = broadcast.map(|server| {
+        let time_control = game_state
+            .clock
+            .as_ref()
+            .map_or(TimeControl::Unlimited, Clock::time_control);
+        let id = server.watch(&game_state, time_control);
+        (server, id)
+    });
+    let mut notice: Option<String> = None;
+    let screen = tui::enabled().then(tui::Screen::enter);
+    let outcome = loop {
+        tui::redraw();
+        // An empty line without a clock keeps the board where the cursor
+        // expects it.
+        match &game_state.clock {
+            Some(clock) => clock.draw(),
+            None => outln!(),
+        }
+        // Whoever sits at the terminal gets their pieces at the bottom.
+        let home = if players.black.is_interactive() && !players.white.is_interactive() {
+            Color::Black
+        } else {
+            Color::White
+        };
+        let to_move = game_state.current_player;
+        let bottom = if flip && players.get(to_move).is_interactive() {
+            to_move
+        } else {
+            home
+        };
+        BoardView {
+            board: &game_state.board,
+            bottom,
+            highlights: Highlights::of(&game_state),
+        }
+        .draw();
+        players.get_mut(to_move).board_shown(bottom);
+        Panel {
+            game_state: &game_state,
+            players: &players,
+        }
+        .draw();
+        for color in [Color::White, Color::Black] {
+            if let Some(info) = players.get(color).info() {
+                outln!("{}'s {}", color, info);
+            }
+        }
+        if let Some(notice) = notice.take() {
+            outln!("{}", notice);
+        }
+
+        console::flush();
+        game_state = game_state.do_move(&mut players);
+        // A player who saved or loaded the game is still to move.
+        let request = players.get(to_move).request();
+        match &request {
+            Some(Request::Exit) => break "The game was stopped.".to_string(),
+            Some(Request::Save(path)) => {
+                notice = Some(
+                    SavedGame::new(&game_state, &players)
+                        .and_then(|saved| saved.save(path))
+                        .map_or_else(|error| error, |_| format!("Saved the game to {}.", path)),
+                );
+            }
+            Some(Request::Load(path)) => match load(path) {
+                Ok(loaded) => {
+                    (players, game_state) = loaded;
+                    notice = Some(format!("Loaded the game from {}.", path));
+                }
+                Err(error) => notice = Some(error),
+            },
+            None => {}
+        }
+        if let Some((server, id)) = &broadcast {
+            server.update(*id, &game_state);
+        }
+        if let Some(outcome) = game_state.outcome {
+            break outcome.describe(|color| players.label(color));
+        }
+        if request.is_none() {
+            game_state = game_state.switch_player();
+        }
+    };
+    outln!("{}", outcome);
+    console::read_line();
+    if screen.is_some() {
+        drop(screen);
+        outln!("{}", outcome);
+    }
+    (game_state, players)
+}
+
+/// How long the engine looks at each position of a finished game.
+const ANALYSIS_TIME: Duration = Duration::from_millis(300);
+
+/// How the engine analyses games: for `ANALYSIS_TIME` per position, or to
+/// exactly `--depth` if given.
+fn analysis_options(args: &[String]) -> Result<SearchOptions, String> {
+    let default = SearchOptions::default();
+    let depth = parsed::<u32>(args, "--depth")?;
+    Ok(SearchOptions {
+        depth: depth.unwrap_or(6),
+        movetime: if depth.is_some() {
+            None
+        } else {
+            Some(ANALYSIS_TIME)
+        },
+        threads: parsed(args, "--threads")?.unwrap_or(default.threads),
+        ..default
+    })
+}
+
+/// Runs the engine over the game, showing how far it got.
+fn analyze_with_progress(game_state: &GameState, options: SearchOptions) -> Analysis {
+    let analysis = analyze(game_state, options, |done, total| {
+        print!("\rAnalysing position {} of {}...", done + 1, total);
         io::stdout().flush().unwrap();
-        game_state = game_state.do_move();
-        game_state = game_state.switch_player();
-        if game_state.checkmate {
-            println!("Checkmate! {} won!", game_state.current_player);
-            break;
+    });
+    println!("\n");
+    analysis
+}
+
+/// The tags for a game played here, with the position it started from
+/// unless that was the standard one and the seed to play it again.
+fn game_tags(
+    game_state: &GameState,
+    players: &Players,
+    result: &str,
+) -> Vec<(&'static str, String)> {
+    let mut tags = vec![
+        ("Event", "Casual game".to_string()),
+        ("White", players.label(Color::White)),
+        ("Black", players.label(Color::Black)),
+        ("Result", result.to_string()),
+    ];
+    let fen = game_state.start_fen();
+    if fen != STARTING_FEN {
+        tags.extend([("SetUp", "1".to_string()), ("FEN", fen)]);
+    }
+    if let Some(seed) = game_state.seed {
+        tags.push(("Seed", seed.to_string()));
+    }
+    tags
+}
+
+/// Prints how well each side played, a graph of the evaluation and the game
+/// annotated with the engine's verdicts, and writes that to `output` too if
+/// given.
+fn print_analysis(
+    analysis: &Analysis,
+    game_state: &GameState,
+    label: impl Fn(Color) -> String,
+    tags: &[(&str, String)],
+    result: &str,
+    output: Option<&String>,
+) -> Result<(), String> {
+    out!("{}", analysis.summary(label));
+    outln!();
+    EvalGraph::new(
+        &game_state.start_board,
+        game_state.start_player,
+        game_state.first_move,
+        &analysis.moves(),
+        analysis.evaluations(),
+    )
+    .draw();
+
+    let pgn = analysis.pgn(tags, result);
+    outln!("\n{}", pgn.trim_end());
+    if let Some(path) = output {
+        std::fs::write(path, pgn)
+            .map_err(|error| format!("Could not write {}: {}", path, error))?;
+    }
+    Ok(())
+}
+
+/// `--analyze` has the engine go over the game once it is finished, and
+/// writes the annotated game to `--save-pgn` if given.
+fn analyze_game(args: &[String], game_state: &GameState, players: &Players) -> Result<(), String> {
+    let analysis = analyze_with_progress(game_state, analysis_options(args)?);
+    let result = game_state.outcome.map_or("*", |outcome| outcome.result());
+    print_analysis(
+        &analysis,
+        game_state,
+        |color| players.label(color),
+        &game_tags(game_state, players, result),
+        result,
+        arg_value(args, "--save-pgn"),
+    )
+}
+
+/// Reads the first game in the PGN file at `path` and plays it through.
+fn game_from_pgn(path: &str) -> Result<(PgnGame, GameState), String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| format!("Could not read {}: {}", path, error))?;
+    let game = read_pgn(&text).map_err(|error| format!("Could not read {}: {}", path, error))?;
+    let mut game_state = GameState::from_position(
+        game.start_board.clone(),
+        game.start_player,
+        TimeControl::Unlimited,
+    );
+    game_state.first_move = game.tag("FEN").map_or(1, fullmove_number);
+    for mv in &game.moves {
+        game_state = game_state.play(*mv)?.switch_player();
+    }
+    Ok((game, game_state))
+}
+
+/// The players' names from the tags of `game`.
+fn pgn_label(game: &PgnGame) -> impl Fn(Color) -> String + '_ {
+    |color: Color| {
+        let name = game.tag(&color.to_string()).filter(|name| !name.is_empty());
+        engine::controller::player_label(name, color)
+    }
+}
+
+/// `analyze <file>` has the engine go over a game saved as PGN, the same as
+/// `--analyze` at the end of a game, and writes the annotated game to
+/// `--output` if given.
+fn analyze_from_args(args: &[String]) -> Result<(), String> {
+    theme::set(theme_from_args(args)?);
+    let path = arg_value(args, "--pgn").ok_or("analyze needs a PGN file")?;
+    let (game, game_state) = game_from_pgn(path)?;
+    let analysis = analyze_with_progress(&game_state, analysis_options(args)?);
+    let tags: Vec<(&str, String)> = game
+        .tags
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .collect();
+    print_analysis(
+        &analysis,
+        &game_state,
+        pgn_label(&game),
+        &tags,
+        &game.result,
+        arg_value(args, "--output"),
+    )
+}
+
+/// `review <file>` (or `--review <file>`) draws a graph of the evaluation over the game in the
+/// PGN file. It uses the `[%eval]` comments if every move has one, as in
+/// games saved with `--analyze`, and has the engine analyse the game
+/// otherwise.
+fn review_from_args(args: &[String]) -> Result<(), String> {
+    theme::set(theme_from_args(args)?);
+    let path = arg_value(args, "--pgn").ok_or("review needs a PGN file")?;
+    let (game, game_state) = game_from_pgn(path)?;
+    let evaluations: Option<Vec<i32>> = game
+        .comments
+        .iter()
+        .map(|comment| comment.as_deref().and_then(eval_in))
+        .collect();
+    let evaluations = match evaluations {
+        Some(evaluations) => evaluations,
+        None => {
+            let analysis = analyze_with_progress(&game_state, analysis_options(args)?);
+            println!("{}", analysis.summary(pgn_label(&game)));
+            analysis.evaluations()
         }
+    };
+    EvalGraph::new(
+        &game.start_board,
+        game.start_player,
+        game_state.first_move,
+        &game.moves,
+        evaluations,
+    )
+    .draw();
+    Ok(())
+}
+
+/// `perft <depth>` counts the games of that many plies from `--fen` or the
+/// standard position, after each first move and in total.
+fn perft_from_args(args: &[String]) -> Result<(), String> {
+    let depth = parsed::<u32>(args, "--depth")?
+        .filter(|depth| *depth > 0)
+        .ok_or("perft needs a depth of at least 1")?;
+    let (board, player) = from_fen(arg_value(args, "--fen").map_or(STARTING_FEN, String::as_str))?;
+    let started = Instant::now();
+    let mut total = 0;
+    for mv in legal_moves(&board, &player) {
+        let count = perft(&apply_move(board.clone(), &mv), player.switch(), depth - 1);
+        println!("{}: {}", mv, count);
+        total += count;
+    }
+    println!(
+        "\nTotal: {} in {:.2}s",
+        total,
+        started.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+/// `bench` searches the positions in `BENCH_POSITIONS` to `--depth` on
+/// `--threads` (one by default, so that the node count stays the same) and
+/// reports the speed.
+fn bench_from_args(args: &[String]) -> Result<(), String> {
+    let options = SearchOptions {
+        threads: parsed(args, "--threads")?.unwrap_or(1),
+        depth: parsed(args, "--depth")?.unwrap_or(5),
+        movetime: None,
+        ..Default::default()
+    };
+    let started = Instant::now();
+    let mut nodes = 0;
+    for (index, fen) in BENCH_POSITIONS.iter().enumerate() {
+        let (board, player) = from_fen(fen)?;
+        let result = Engine::new(options).search(&board, player);
+        println!("Position {}: {}", index + 1, result);
+        nodes += result.nodes;
     }
-    let mut line = String::new();
-    std::io::stdin().read_line(&mut line).unwrap();
+    let seconds = started.elapsed().as_secs_f64();
+    println!(
+        "\n{} nodes in {:.2}s, {:.0} nodes per second",
+        nodes,
+        seconds,
+        nodes as f64 / seconds.max(0.001)
+    );
+    Ok(())
 }
 
 #[test]
@@ -43,17 +870,65 @@ fn board_placement_test() {
         .add_replace_piece(
             pawn_move_coordinates,
             ChessPiece {
-                player: engine::Player::User,
+                player: engine::Color::White,
                 piece_type: board::chesspiece::ChessPieceType::Pawn,
             },
         )
         .add_replace_piece(
             bishop_move_coordinates,
             ChessPiece {
-                player: engine::Player::User,
+                player: engine::Color::White,
                 piece_type: board::chesspiece::ChessPieceType::Bishop,
             },
         );
 
     board.draw();
 }
+
+#[test]
+fn scripted_game() {
+    let play_from = |fen: &str, script: &str| {
+        console::capture(script, || {
+            let (board, player) = from_fen(fen).unwrap();
+            let players = Players {
+                white: Box::new(HumanController::default()),
+                black: Box::new(HumanController::default()),
+            };
+            let game_state = GameState::from_position(board, player, TimeControl::Unlimited);
+            game_engine(players, game_state, false, None, |_| {
+                Err("There are no saves.".to_string())
+            });
+        })
+    };
+    let play = |script: &str| play_from("7k/8/6K1/8/8/8/8/R7 w - - 0 1", script);
+    let output = play("score\ng6\nback\nfoo\na1\na8\n\n");
+    assert!(output.contains("score\nMaterial: White 5, Black 0\n"));
+    assert!(output.contains("foo\nInvalid, try again. Select a field with your figure\n"));
+    assert!(output.contains("8|R| | | | | | |k|8"));
+    assert!(output.ends_with("Checkmate! White won!\n\n"));
+
+    // The game stops where the script ends.
+    let output = play("a1\n");
+    assert!(output.ends_with("The game was stopped.\n"));
+    assert!(!output.contains("won"));
+
+    // Also while choosing the piece for a pawn.
+    let output = play_from("7k/P7/8/8/8/8/8/K7 w - - 0 1", "a7\na8\n");
+    assert!(output.ends_with("Choose a piece to replace the pawn: \nThe game was stopped.\n"));
+}
+
+#[test]
+fn rejects_invalid_engine_strength() {
+    let players = |args: &[&str]| {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        players_from_args(&args, 0).map(|_| ())
+    };
+    assert_eq!(
+        players(&["--skill", "25"]),
+        Err("Invalid --skill: Level must be between 1 and 20: 25".to_string())
+    );
+    assert_eq!(
+        players(&["--elo", "strong"]),
+        Err("Invalid --elo: strong".to_string())
+    );
+}